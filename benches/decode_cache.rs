@@ -0,0 +1,30 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use chip8_emulator::Chip8;
+
+const CYCLES: u64 = 10_000;
+const TRIP8: &[u8] = include_bytes!("../static/roms/trip8.ch8");
+
+fn run(rom: &[u8], cache: bool) -> u64 {
+    let mut chip8 = Chip8::from_bytes(rom);
+    chip8.set_seed(1);
+    chip8.set_decode_cache(cache);
+    for _ in 0..CYCLES{
+        chip8.tick();
+    }
+    chip8.state_hash()
+}
+
+fn decode_cache(c: &mut Criterion){
+    let mut group = c.benchmark_group("decode_cache");
+    group.throughput(Throughput::Elements(CYCLES));
+    for cache in [false, true]{
+        let name = if cache {"cached"} else {"uncached"};
+        group.bench_with_input(BenchmarkId::new(name, "trip8"), &cache, |b, cache| {
+            b.iter(|| run(TRIP8, *cache))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_cache);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use chip8_emulator::Chip8;
+use chip8_emulator::engine::Engine;
+
+const CYCLES: u32 = 10_000;
+const ROMS: [(&str, &[u8]); 3] = [
+    ("trip8", include_bytes!("../static/roms/trip8.ch8")),
+    ("life", include_bytes!("../static/roms/life.ch8")),
+    ("triangle", include_bytes!("../static/roms/triangle.ch8"))
+];
+
+fn engines(c: &mut Criterion){
+    let mut group = c.benchmark_group("engines");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+    for (name, rom) in ROMS{
+        for engine in [Engine::Interpreter, Engine::Threaded]{
+            // build the machine once so only execution is measured
+            let mut chip8 = Chip8::from_bytes(rom);
+            chip8.set_seed(1);
+            chip8.set_decode_cache(false);
+            chip8.set_engine(engine);
+            let start = chip8.save_state();
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", engine), name), &start, |b, start| {
+                b.iter(|| {
+                    chip8.load_state(start).unwrap();
+                    chip8.run(CYCLES);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
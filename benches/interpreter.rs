@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use chip8_emulator::Chip8;
+use chip8_emulator::state::SaveState;
+
+const CYCLES: u32 = 10_000;
+const DRAW_LOOPS: u32 = 1_000;
+// 0x200: A050 I = font
+// 0x202: D01F draw 15 rows at V0,V1
+// 0x204: 7003 V0 += 3
+// 0x206: 7105 V1 += 5
+// 0x208: 1202 loop
+const DRAW_LOOP: [u8; 10] = [0xA0, 0x50, 0xD0, 0x1F, 0x70, 0x03, 0x71, 0x05, 0x12, 0x02];
+
+fn roms() -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("static/roms");
+    let mut roms: Vec<(String, Vec<u8>)> = fs::read_dir(dir).expect("static/roms exists")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ch8"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect();
+    roms.sort();
+    roms
+}
+
+fn booted(rom: &[u8]) -> (Chip8, SaveState) {
+    let mut chip8 = Chip8::from_bytes(rom);
+    chip8.set_seed(1);
+    let start = chip8.save_state();
+    (chip8, start)
+}
+
+fn roms_headless(c: &mut Criterion){
+    let mut group = c.benchmark_group("roms");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+    for (name, rom) in roms(){
+        let (mut chip8, start) = booted(&rom);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                chip8.load_state(&start).unwrap();
+                // ten instructions per 60Hz frame like the web frontend
+                for _ in 0..CYCLES / 10{
+                    chip8.run_frame(10);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn draw_heavy(c: &mut Criterion){
+    let mut group = c.benchmark_group("draw");
+    group.throughput(Throughput::Elements(DRAW_LOOPS as u64));
+    let (mut chip8, start) = booted(&DRAW_LOOP);
+    group.bench_function("dxyn_15_rows", |b| {
+        b.iter(|| {
+            chip8.load_state(&start).unwrap();
+            chip8.run(1 + DRAW_LOOPS * 4);
+        })
+    });
+    group.finish();
+}
+
+fn save_load(c: &mut Criterion){
+    let (mut chip8, _) = booted(include_bytes!("../static/roms/trip8.ch8"));
+    chip8.run(CYCLES);
+    let state = chip8.save_state();
+    let json = state.to_json();
+    let mut group = c.benchmark_group("state");
+    group.bench_function("save", |b| b.iter(|| chip8.save_state()));
+    group.bench_function("load", |b| b.iter(|| chip8.load_state(&state).unwrap()));
+    group.bench_function("to_json", |b| b.iter(|| state.to_json()));
+    group.bench_function("from_json", |b| b.iter(|| SaveState::from_json(&json).unwrap()));
+    group.bench_function("state_hash", |b| b.iter(|| chip8.state_hash()));
+    group.finish();
+}
+
+criterion_group!(benches, roms_headless, draw_heavy, save_load);
+criterion_main!(benches);
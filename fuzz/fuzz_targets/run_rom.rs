@@ -0,0 +1,49 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use chip8_emulator::engine::Engine;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::{Chip8, KeyState};
+use libfuzzer_sys::fuzz_target;
+
+// keeps a single run short enough for libFuzzer to explore quickly
+const MAX_CYCLES: u32 = 2000;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    rom: Vec<u8>,
+    keys: Vec<(u8, bool, u8)>,
+    seed: u64,
+    quirks: u8,
+    cycles: u16
+}
+
+fn boot(input: &Input, engine: Engine) -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(&input.rom);
+    chip8.set_seed(input.seed);
+    let bit = |n: u8| input.quirks & (1 << n) != 0;
+    chip8.set_quirks(Quirks {
+        key_wait_on_press: bit(0),
+        shift_uses_vy: bit(1),
+        logic_resets_vf: bit(2),
+        memory_increments_index: bit(3),
+        jump_uses_vx: bit(4),
+        wrap_sprites: bit(5)
+    });
+    chip8.set_engine(engine);
+    for (key, pressed, time) in input.keys.iter(){
+        let state = if *pressed {KeyState::ON} else {KeyState::OFF};
+        chip8.queue_key_event(*key as usize, state, *time as f64);
+    }
+    return chip8;
+}
+
+fuzz_target!(|input: Input| {
+    let cycles = (input.cycles as u32) % MAX_CYCLES;
+    let mut interpreter = boot(&input, Engine::Interpreter);
+    let mut threaded = boot(&input, Engine::Threaded);
+    interpreter.run_frame(cycles);
+    threaded.run_frame(cycles);
+    // both engines have to agree on whatever the ROM did
+    assert_eq!(interpreter.state_hash(), threaded.state_hash());
+    assert_eq!(interpreter.state_hash(), interpreter.incremental_state_hash());
+});
@@ -0,0 +1,34 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use chip8_emulator::state::SaveState;
+use chip8_emulator::Chip8;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    rom: Vec<u8>,
+    seed: u64,
+    cycles: u16,
+    json: String
+}
+
+fuzz_target!(|input: Input| {
+    let mut chip8 = Chip8::from_bytes(&input.rom);
+    chip8.set_seed(input.seed);
+    chip8.run_frame((input.cycles % 2000) as u32);
+
+    // a state must survive the trip through JSON and back unchanged
+    let saved = chip8.save_state();
+    let restored = SaveState::from_json(&saved.to_json()).expect("own save state parses");
+    let mut other = Chip8::from_bytes(&input.rom);
+    other.load_state(&restored).expect("own save state loads");
+    assert_eq!(chip8.state_hash(), other.state_hash());
+    assert_eq!(saved.state_hash(), restored.state_hash());
+
+    // whatever a user uploads may be rejected, but must never panic
+    if let Ok(state) = SaveState::from_json(&input.json){
+        if chip8.load_state(&state).is_ok(){
+            chip8.run_frame(100);
+        }
+    }
+});
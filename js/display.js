@@ -1,9 +1,11 @@
-const height = 32;
+var height = 32;
 const width = 64;
 const PIXEL_SIZE = 8;
 const GRID_COLOR = "#CCCCCC";
-const OFF_COLOR = "#000000";
-const ON_COLOR = "#48ff00";
+const DEFAULT_OFF_COLOR = "#000000";
+const DEFAULT_ON_COLOR = "#48ff00";
+var offColor = DEFAULT_OFF_COLOR;
+var onColor = DEFAULT_ON_COLOR;
 const canvas = document.getElementById("chip8-screen");
 
 const ctx = canvas.getContext('2d');
@@ -11,6 +13,12 @@ canvas.height = (PIXEL_SIZE + 1) * height + 1;
 canvas.width = (PIXEL_SIZE + 1) * width + 1;
 
 
+// colors from the ROM database: background first, then the pixel color
+export function setColors(colors){
+    offColor = colors.length > 0 ? colors[0] : DEFAULT_OFF_COLOR;
+    onColor = colors.length > 1 ? colors[1] : DEFAULT_ON_COLOR;
+}
+
 export function drawGrid() {
     ctx.beginPath();
     ctx.strokeStyle = GRID_COLOR;
@@ -33,7 +41,36 @@ function getIndex(row, column) {
     return row * width + column;
 };
   
+// MEGA-CHIP frames are copied here, then scaled onto the screen
+const megaCanvas = document.createElement("canvas");
+const megaCtx = megaCanvas.getContext('2d');
+
+function drawFramebuffer(chip8, memory) {
+    const megaWidth = chip8.get_display_width();
+    const megaHeight = chip8.get_display_height();
+    megaCanvas.width = megaWidth;
+    megaCanvas.height = megaHeight;
+    const rgba = new Uint8ClampedArray(memory.buffer, chip8.get_framebuffer(), megaWidth * megaHeight * 4);
+    megaCtx.putImageData(new ImageData(rgba, megaWidth, megaHeight), 0, 0);
+    ctx.fillStyle = DEFAULT_OFF_COLOR;
+    ctx.fillRect(0, 0, canvas.width, canvas.height);
+    ctx.globalAlpha = chip8.get_screen_alpha() / 255;
+    ctx.imageSmoothingEnabled = false;
+    ctx.drawImage(megaCanvas, 0, 0, canvas.width, canvas.height);
+    ctx.globalAlpha = 1;
+}
+
 export function drawPixels(chip8, memory, mod) {
+    if (chip8.get_megachip_mode()) {
+        drawFramebuffer(chip8, memory);
+        return;
+    }
+    // HIRES programs switch to 64 rows
+    if (chip8.get_display_height() !== height) {
+        height = chip8.get_display_height();
+        canvas.height = (PIXEL_SIZE + 1) * height + 1;
+        drawGrid();
+    }
     const pixelsPtr = chip8.get_display()
     const pixels = new Uint8Array(memory.buffer, pixelsPtr, width * height);
     ctx.beginPath();
@@ -42,8 +79,8 @@ export function drawPixels(chip8, memory, mod) {
         const idx = getIndex(row, col);
 
         ctx.fillStyle = pixels[idx] === mod.PixelState.OFF
-            ? OFF_COLOR
-            : ON_COLOR;
+            ? offColor
+            : onColor;
 
         ctx.fillRect(
             col * (PIXEL_SIZE + 1) + 1,
@@ -65,4 +102,16 @@ export function updateRegisters(chip8) {
     let val = regComp.getElementsByClassName('reg-val')[0].getElementsByTagName('p')[0]
     val.textContent = regVal
   }
+  let line = chip8.get_source_line()
+  document.getElementById('pc-symbol').textContent = `PC ${chip8.symbolize_pc()}`
+    + (line !== undefined ? ` (line ${line})` : '')
+}
+
+export function showBreakpoints(chip8, paused) {
+  let names = chip8.symbolize_breakpoints()
+  document.getElementById('breakpoints').textContent = names.length > 0
+    ? `Breakpoints: ${names.join(', ')}` : 'No breakpoints'
+  document.getElementById('break-status').textContent = paused
+    ? `Stopped at ${chip8.symbolize(chip8.get_break_hit())}` : ''
+  document.getElementById('continue-button').disabled = !paused
 }
\ No newline at end of file
@@ -1,20 +1,31 @@
-import { drawGrid, drawPixels, updateRegisters } from './display';
-import { keyBoardSetUp } from './keyboard';
+import { drawGrid, drawPixels, setColors, showBreakpoints, updateRegisters } from './display';
+import { keyBoardSetUp, loadProfile, pollGamepads, remapKey } from './keyboard';
 import {play} from './audio';
 const wasm = import('../pkg')
 const wasm_memory = import('../pkg/index_bg.wasm')
 var memory;
 var chip8;
 var mod;
+var keymap;
+var profile;
+var romName;
+var ticksPerFrame = 10;
+// set when a run stops at a breakpoint, until Continue is pressed
+var paused = false;
 
 
 function render() {
-    for(let i=0; i < 10; i++){
-        chip8.tick();
-        updateRegisters(chip8)
+    pollGamepads(chip8, keymap, mod);
+    if(!paused){
+        chip8.run(ticksPerFrame);
+        paused = chip8.get_break_hit() !== undefined;
+        if(!paused){
+            chip8.tick_timers();
+        }
     }
+    updateRegisters(chip8);
+    showBreakpoints(chip8, paused);
     drawGrid();
-    chip8.tick_timers();
     drawPixels(chip8, memory, mod);
     requestAnimationFrame(render);
 }
@@ -34,14 +45,128 @@ function buf2hex(buffer) { // buffer is an ArrayBuffer
         .join('');
 }
 
+function showRomInfo(info){
+    let label = document.getElementById('rom-info');
+    if(info === undefined){
+        label.textContent = 'Unknown ROM';
+        return;
+    }
+    let authors = info.get_authors();
+    let release = info.get_release();
+    label.textContent = info.get_title()
+        + (authors.length > 0 ? ` by ${authors.join(', ')}` : '')
+        + (release !== undefined ? ` (${release})` : '');
+}
+
 async function start(rom){
    mod = await wasm;
    memory = (await wasm_memory).memory;
    let loadedRom = await loadRom(rom);
    chip8 = await initChip8(loadedRom);
-   keyBoardSetUp(chip8, mod);
+   let info = chip8.get_rom_info();
+   showRomInfo(info);
+   ticksPerFrame = info?.get_tick_rate() ?? 10;
+   setColors(info === undefined ? [] : info.get_pixel_colors());
+   romName = rom;
+   profile = loadProfile(rom, mod, info);
+   try {
+       chip8.apply_profile(profile);
+   }
+   catch(err){
+       // the quirks still apply, the ROM stays where it was loaded
+       console.error(`Keeping the default layout for ${rom}: ${err}`);
+   }
+   keymap = profile.get_keymap();
+   keyBoardSetUp(chip8, keymap, mod);
+   paused = false;
    render();
 }
+// Octo's own options.json, as typed or from the last cartridge opened
+function octoOptions(){
+    let json = document.getElementById('octo-options').value.trim();
+    return mod.OctoOptions.from_json(json === '' ? '{}' : json);
+}
+
+// assembles the editor contents into the running emulator, or starts one
+function loadOcto(){
+    let status = document.getElementById('octo-status');
+    let source = document.getElementById('octo-source').value;
+    try {
+        let options = octoOptions();
+        if(chip8 === undefined){
+            let program = mod.assemble_octo(source);
+            chip8 = mod.Chip8.new(new Uint8Array(program.get_rom()));
+            chip8.set_symbols(program.get_symbols());
+            paused = false;
+            render();
+        }
+        else {
+            chip8.load_octo(source);
+        }
+        // applied the way start() applies a known ROM's settings
+        chip8.apply_octo_options(options);
+        ticksPerFrame = options.get_tick_rate();
+        setColors(options.get_pixel_colors());
+        keymap = options.get_keymap();
+        keyBoardSetUp(chip8, keymap, mod);
+        status.textContent = 'Loaded';
+    }
+    catch(err){
+        status.textContent = err;
+    }
+}
+
+// an Octo cartridge GIF fills in the source and brings its options along
+document.getElementById('octo-cartridge').addEventListener('change', async (event)=>{
+    let file = event.target.files[0];
+    if(file === undefined){
+        return;
+    }
+    let status = document.getElementById('octo-status');
+    try {
+        mod = await wasm;
+        let cartridge = mod.OctoCartridge.from_gif(new Uint8Array(await file.arrayBuffer()));
+        document.getElementById('octo-source').value = cartridge.get_source();
+        document.getElementById('octo-options').value = cartridge.get_options().to_json();
+        status.textContent = `Opened ${file.name}`;
+    }
+    catch(err){
+        status.textContent = err;
+    }
+})
+
+// the next key pressed is bound to the chosen CHIP-8 key
+document.getElementById('remap-button').addEventListener('click', ()=>{
+    if(profile === undefined){
+        return;
+    }
+    let key = parseInt(document.getElementById('remap-key').value, 16);
+    let status = document.getElementById('remap-status');
+    status.textContent = `Press a key for ${key.toString(16).toUpperCase()}`;
+    document.addEventListener('keydown', (event)=>{
+        // the key being bound must not also reach the game
+        event.preventDefault();
+        event.stopImmediatePropagation();
+        remapKey(romName, profile, keymap, event.code, key, mod);
+        status.textContent = `${event.code} -> ${key.toString(16).toUpperCase()}`;
+    }, {once: true, capture: true});
+})
+
+// runs on from the breakpoint the program stopped at
+document.getElementById('continue-button').addEventListener('click', ()=>{
+    paused = false;
+})
+
+document.getElementById('octo-button').addEventListener('click', ()=>{
+    wasm.then((loaded)=>{
+        mod = loaded;
+        return wasm_memory;
+    }).then((loaded)=>{
+        memory = loaded.memory;
+        loadOcto();
+    }).catch(console.error)
+})
+
 let button = document.getElementById('start-button')
 button.addEventListener('click',()=>{
     start(document.getElementById('rom-select').value).then().catch(console.error)
@@ -1,40 +1,90 @@
+const PROFILE_PREFIX = 'chip8-profile:';
+var gamepadState = {};
 
-const keyMap = {
-    'KeyQ':0,
-    'KeyW':1,
-    'KeyE':2,
-    'KeyR':3,
-    'KeyT':4,
-    'KeyA':5,
-    'KeyS':6,
-    'KeyD':7,
-    'KeyF':8,
-    'KeyG':9,
-    'KeyZ':0xA,
-    'KeyX':0xB,
-    'KeyC':0xC,
-    'KeyV':0xD,
-    'KeyB':0xE,
-    'Space':0xF,
+export function loadProfile(rom, mod, info){
+    let saved = localStorage.getItem(PROFILE_PREFIX + rom);
+    if(saved !== null){
+        try {
+            return mod.RomProfile.from_json(saved);
+        }
+        catch(err){
+            console.error(`Discarding saved profile for ${rom}: ${err}`);
+        }
+    }
+    if(info !== undefined){
+        // known ROMs get their quirks, and their game keys on top of our layout
+        let profile = info.profile();
+        profile.set_keymap(mod.Keymap.standard().with_overrides(info.get_keymap()));
+        return profile;
+    }
+    let profile = mod.RomProfile.new();
+    profile.set_keymap(mod.Keymap.standard());
+    return profile;
+}
+
+export function saveProfile(rom, profile){
+    localStorage.setItem(PROFILE_PREFIX + rom, profile.to_json());
+}
+
+// binds a key on the live keymap and saves it with the ROM's profile
+export function remapKey(rom, profile, keymap, code, key, mod){
+    keymap.bind_key(code, key);
+    // set_keymap takes ownership, so the profile gets a copy
+    profile.set_keymap(keymap.with_overrides(mod.Keymap.empty()));
+    saveProfile(rom, profile);
 }
 
-export function keyBoardSetUp(chip8, mod){
+// the listeners are added once and read whichever machine and keymap were
+// set up last, so restarting or reloading does not stack them
+var listening = null;
+
+export function keyBoardSetUp(chip8, keymap, mod){
+    let first = listening === null;
+    listening = {chip8, keymap, mod};
+    if(!first){
+        return;
+    }
     document.addEventListener('keydown',(event)=>{
-        if(event.code in keyMap){
-            chip8.set_key_state(keyMap[event.code],mod.KeyState.ON);
-            console.log(`pressed Key ${event.code} with map ${keyMap[event.code]}`);
+        if(event.repeat){
+            return;
+        }
+        let key = listening.keymap.map_key(event.code);
+        if(key !== undefined){
+            listening.chip8.queue_key_event(key,listening.mod.KeyState.ON,event.timeStamp);
+            console.log(`pressed Key ${event.code} with map ${key}`);
         }
         else{
             console.error("Pressed unknown key "+ event.code);
         }
      })
      document.addEventListener('keyup',(event)=>{
-        if(event.code in keyMap){
-            chip8.set_key_state(keyMap[event.code],mod.KeyState.OFF);
-            console.log(`pressed Key ${event.code} with map ${keyMap[event.code]}`);
+        let key = listening.keymap.map_key(event.code);
+        if(key !== undefined){
+            listening.chip8.queue_key_event(key,listening.mod.KeyState.OFF,event.timeStamp);
+            console.log(`pressed Key ${event.code} with map ${key}`);
         }
         else{
             console.error("Pressed unknown key "+ event.code);
         }
      })
 }
+
+export function pollGamepads(chip8, keymap, mod){
+    const now = performance.now();
+    for(const pad of navigator.getGamepads()){
+        if(pad === null){
+            continue;
+        }
+        pad.buttons.forEach((button, idx) => {
+            const id = `${pad.index}:${idx}`;
+            if(gamepadState[id] === button.pressed){
+                return;
+            }
+            gamepadState[id] = button.pressed;
+            let key = keymap.map_gamepad_button(idx);
+            if(key !== undefined){
+                chip8.queue_key_event(key, button.pressed ? mod.KeyState.ON : mod.KeyState.OFF, now);
+            }
+        });
+    }
+}
@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE, START_OF_PROG};
+use crate::instructions::Instruction;
+use crate::quirks::Quirks;
+
+use wasm_bindgen::prelude::*;
+
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Platform {
+    #[default]
+    Chip8 = 0,
+    SuperChip = 1,
+    XoChip = 2,
+    // VIP variants, only ever chosen by hand
+    Chip8X = 3,
+    Chip8E = 4,
+    HiresChip8 = 5,
+    MegaChip = 6
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvidenceKind {
+    // 8XY6/8XYE with X != Y, the result depends on `shift_uses_vy`
+    Shift,
+    // I used again after FX55/FX65 without being reloaded, see `memory_increments_index`
+    IndexReuse,
+    // BNNN, see `jump_uses_vx`
+    OffsetJump,
+    // a sprite at a known position crosses the right or bottom edge, see `wrap_sprites`
+    EdgeSprite,
+    SuperChipOpcode,
+    XoChipOpcode
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub addr: usize,
+    pub opcode: u16,
+    pub kind: EvidenceKind,
+    pub detail: String
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Analysis {
+    reachable: usize,
+    evidence: Vec<Evidence>,
+    platform: Platform,
+    quirks: Quirks
+}
+
+#[wasm_bindgen]
+impl Analysis {
+    // number of instructions reached from the entry point
+    pub fn get_reachable(&self) -> usize {
+        return self.reachable;
+    }
+    pub fn get_platform(&self) -> Platform {
+        return self.platform;
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Analysis is always serializable");
+    }
+}
+
+impl Analysis {
+    pub fn evidence(&self) -> &[Evidence] {
+        return &self.evidence;
+    }
+    pub fn has(&self, kind: EvidenceKind) -> bool {
+        return self.evidence.iter().any(|e| e.kind == kind);
+    }
+}
+
+// What the walk knows on the way into an instruction
+#[derive(Clone, Copy)]
+struct Path {
+    addr: usize,
+    // register values set by 6XNN/7XNN since the last jump target
+    known: [Option<u8>; 16],
+    // I as set by ANNN, when nothing has moved it since
+    index: Option<usize>,
+    // address of an FX55/FX65 whose I has not been reloaded since
+    memory_op: Option<usize>
+}
+
+fn opcode_at(memory: &[u8], addr: usize) -> u16 {
+    return u16::from_be_bytes([memory[addr % MEM_SIZE], memory[(addr + 1) % MEM_SIZE]]);
+}
+
+fn superchip_opcode(instr: &Instruction) -> bool {
+    return match instr.operation {
+        0x0 => instr.x == 0 && (instr.y == 0xC || matches!(instr.nn, 0xFB..=0xFF)),
+        0xD => instr.n == 0,
+        0xF => matches!(instr.nn, 0x30 | 0x75 | 0x85),
+        _ => false
+    };
+}
+
+fn xochip_opcode(instr: &Instruction) -> bool {
+    return match instr.operation {
+        0x0 => instr.x == 0 && instr.y == 0xD,
+        0x5 => matches!(instr.n, 2 | 3),
+        0xF => (instr.x == 0 && matches!(instr.nn, 0x00 | 0x02)) || instr.nn == 0x01 || instr.nn == 0x3A,
+        _ => false
+    };
+}
+
+fn uses_index(instr: &Instruction) -> bool {
+    return instr.operation == 0xD || (instr.operation == 0xF && matches!(instr.nn, 0x1E | 0x33 | 0x55 | 0x65));
+}
+
+fn sets_index(instr: &Instruction) -> bool {
+    return instr.operation == 0xA || (instr.operation == 0xF && matches!(instr.nn, 0x29 | 0x30))
+        || (instr.operation == 0xF && instr.x == 0 && instr.nn == 0x00);
+}
+
+// Walks every instruction reachable from the entry point of `rom`, loaded
+// at START_OF_PROG, and collects hints about the quirks it relies on.
+// BNNN targets depend on V0 and are not followed.
+pub fn analyze(rom: &[u8]) -> Analysis {
+    return analyze_at(rom, START_OF_PROG, START_OF_PROG);
+}
+
+pub fn analyze_at(rom: &[u8], load_address: usize, entry_point: usize) -> Analysis {
+    let mut memory = [0_u8; MEM_SIZE];
+    for (i, val) in rom.iter().take(MEM_SIZE.saturating_sub(load_address)).enumerate(){
+        memory[load_address + i] = *val;
+    }
+    let mut visited = vec![false; MEM_SIZE];
+    let mut evidence = Vec::new();
+    let mut reachable = 0;
+    let mut work = vec![Path { addr: entry_point, known: [None; 16], index: None, memory_op: None }];
+
+    while let Some(path) = work.pop(){
+        let addr = path.addr % MEM_SIZE;
+        if visited[addr]{
+            continue;
+        }
+        visited[addr] = true;
+        reachable += 1;
+        let opcode = opcode_at(&memory, addr);
+        let instr = Instruction::from_raw(opcode);
+        let mut note = |kind: EvidenceKind, detail: String| {
+            evidence.push(Evidence { addr, opcode, kind, detail });
+        };
+
+        if superchip_opcode(&instr){
+            note(EvidenceKind::SuperChipOpcode, format!("{:04X} only exists on SCHIP", opcode));
+        }
+        if xochip_opcode(&instr){
+            note(EvidenceKind::XoChipOpcode, format!("{:04X} only exists on XO-CHIP", opcode));
+        }
+        if instr.operation == 0x8 && matches!(instr.n, 0x6 | 0xE) && instr.x != instr.y{
+            note(EvidenceKind::Shift, format!("shifts V{:X} into V{:X}", instr.y, instr.x));
+        }
+        if instr.operation == 0xB{
+            note(EvidenceKind::OffsetJump, format!("jumps to {:03X} plus V0 or V{:X}", instr.nnn, instr.x));
+        }
+        if let Some(memory_op) = path.memory_op{
+            if uses_index(&instr){
+                note(EvidenceKind::IndexReuse, format!("reuses I from {:03X}", memory_op));
+            }
+        }
+        if instr.operation == 0xD{
+            if let (Some(vx), Some(vy)) = (path.known[instr.x as usize], path.known[instr.y as usize]){
+                let (x, y) = (vx as usize % 64, vy as usize % 32);
+                let (width, height) = match path.index {
+                    Some(index) if instr.n > 0 => sprite_extent(&memory, index, instr.n as usize),
+                    // SCHIP draws DXY0 as a 16x16 sprite
+                    _ if instr.n == 0 => (16, 16),
+                    _ => (8, instr.n as usize)
+                };
+                if x + width > 64 || y + height > 32{
+                    note(EvidenceKind::EdgeSprite, format!("draws at {},{} across the edge", x, y));
+                }
+            }
+        }
+
+        // state flowing into the next instruction
+        let mut next = Path { addr: addr + 2, ..path };
+        if instr.operation == 0xF && instr.x == 0 && instr.nn == 0x00{
+            // F000 NNNN is four bytes long
+            next.addr += 2;
+        }
+        match instr.operation {
+            0x6 => next.known[instr.x as usize] = Some(instr.nn),
+            0x7 => next.known[instr.x as usize] = path.known[instr.x as usize].map(|v| v.wrapping_add(instr.nn)),
+            0x8 => {
+                next.known[instr.x as usize] = None;
+                next.known[0xF] = None;
+            }
+            0xC => next.known[instr.x as usize] = None,
+            0xD => next.known[0xF] = None,
+            0xF if matches!(instr.nn, 0x07 | 0x0A) => next.known[instr.x as usize] = None,
+            0xF if instr.nn == 0x65 => next.known = [None; 16],
+            _ => {}
+        }
+        if sets_index(&instr){
+            next.memory_op = None;
+        }
+        if instr.operation == 0xA{
+            next.index = Some(instr.nnn as usize);
+        }
+        else if sets_index(&instr) || (instr.operation == 0xF && matches!(instr.nn, 0x1E | 0x55 | 0x65)){
+            next.index = None;
+        }
+        if instr.operation == 0xF && matches!(instr.nn, 0x55 | 0x65){
+            next.memory_op = Some(addr);
+        }
+        // a jump or call target can be reached with any register values
+        let target = Path { addr: instr.nnn as usize, known: [None; 16], index: None, ..next };
+
+        match instr.operation {
+            0x0 if instr.nnn == 0x0EE || instr.nnn == 0x0FD => {}
+            0x1 => work.push(target),
+            0x2 => {
+                work.push(next);
+                work.push(target);
+            }
+            0xB => {}
+            0x3 | 0x4 | 0x9 => {
+                work.push(next);
+                work.push(Path { addr: next.addr + skip_len(&memory, next.addr), ..next });
+            }
+            0x5 if instr.n == 0 => {
+                work.push(next);
+                work.push(Path { addr: next.addr + skip_len(&memory, next.addr), ..next });
+            }
+            0xE if matches!(instr.nn, 0x9E | 0xA1) => {
+                work.push(next);
+                work.push(Path { addr: next.addr + skip_len(&memory, next.addr), ..next });
+            }
+            _ => work.push(next)
+        }
+    }
+
+    let platform = if evidence.iter().any(|e| e.kind == EvidenceKind::XoChipOpcode) {
+        Platform::XoChip
+    } else if evidence.iter().any(|e| e.kind == EvidenceKind::SuperChipOpcode) {
+        Platform::SuperChip
+    } else {
+        Platform::Chip8
+    };
+    let preset = match platform {
+        Platform::SuperChip => Quirks::schip(),
+        Platform::XoChip => Quirks::xochip(),
+        _ => Quirks::vip()
+    };
+    // Evidence shows which quirks the program depends on but not which way,
+    // so those follow the platform and the rest keep their defaults. The VF
+    // reset leaves no trace in the code and always follows the platform. A
+    // sprite drawn across an edge at a known position expects to wrap.
+    let has = |kind: EvidenceKind| evidence.iter().any(|e| e.kind == kind);
+    let mut quirks = Quirks { logic_resets_vf: preset.logic_resets_vf, ..Quirks::default() };
+    if has(EvidenceKind::Shift){
+        quirks.shift_uses_vy = preset.shift_uses_vy;
+    }
+    if has(EvidenceKind::IndexReuse){
+        quirks.memory_increments_index = preset.memory_increments_index;
+    }
+    if has(EvidenceKind::OffsetJump){
+        quirks.jump_uses_vx = preset.jump_uses_vx;
+    }
+    quirks.wrap_sprites = has(EvidenceKind::EdgeSprite);
+    return Analysis { reachable, evidence, platform, quirks };
+}
+
+// columns and rows a sprite actually covers, ignoring blank right columns
+// and bottom rows
+fn sprite_extent(memory: &[u8], index: usize, rows: usize) -> (usize, usize) {
+    let (mut width, mut height) = (0, 0);
+    for row in 0..rows{
+        let byte = memory[(index + row) % MEM_SIZE];
+        if byte != 0{
+            width = width.max(8 - byte.trailing_zeros() as usize);
+            height = row + 1;
+        }
+    }
+    return (width, height);
+}
+
+// XO-CHIP skips hop over the whole four byte F000 NNNN
+fn skip_len(memory: &[u8], addr: usize) -> usize {
+    return if opcode_at(memory, addr) == 0xF000 {4} else {2};
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn analyze_rom(&self) -> Analysis {
+        return analyze_at(&self.rom, self.load_address, self.entry_point);
+    }
+}
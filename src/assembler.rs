@@ -0,0 +1,875 @@
+use std::collections::{HashMap, VecDeque};
+use crate::{Chip8, START_OF_PROG};
+use crate::octo::OctoCartridge;
+use crate::symbols::SymbolTable;
+
+use wasm_bindgen::prelude::*;
+
+
+// XO-CHIP programs may fill the whole 64K address space
+const ADDRESS_SPACE: usize = 0x10000;
+// macros may call macros, but a macro that calls itself never bottoms out
+const MAX_MACRO_DEPTH: u32 = 64;
+
+// The output of `assemble`: the ROM image for its load address and the
+// labels and source lines of its addresses
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoProgram {
+    rom: Vec<u8>,
+    symbols: SymbolTable
+}
+
+#[wasm_bindgen]
+impl OctoProgram {
+    pub fn get_rom(&self) -> Vec<u8> {
+        return self.rom.clone();
+    }
+    pub fn get_symbols(&self) -> SymbolTable {
+        return self.symbols.clone();
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: u32,
+    // how many macro expansions produced the token
+    depth: u32
+}
+
+#[derive(Clone)]
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>
+}
+
+enum Patch {
+    // the NNN of the instruction at the address
+    Nnn,
+    // the 16 bit word after F000
+    Long,
+    // the address halves loaded by the two instructions of `:unpack`
+    Unpack
+}
+
+struct Fixup {
+    addr: usize,
+    patch: Patch,
+    name: String,
+    line: u32
+}
+
+enum Flow {
+    // `if ... begin`, with the jump taken when the condition fails
+    If(usize),
+    // `else`, with the jump at the end of the `begin` half
+    Else(usize),
+    // `loop`, with the jumps out of it emitted by `while`
+    Loop(usize, Vec<usize>)
+}
+
+// A compare as the instructions that skip the next one when it holds or
+// fails, plus any setup they need
+struct Condition {
+    prelude: Vec<u16>,
+    skip_if_true: u16,
+    skip_if_false: u16
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (i, text) in source.lines().enumerate(){
+        let line = i as u32 + 1;
+        let mut chars = text.chars().peekable();
+        let mut current = String::new();
+        while let Some(c) = chars.next(){
+            if c == '#' && current.is_empty(){
+                break;
+            }
+            if c == '"' && current.is_empty(){
+                // strings keep their spaces, quotes included
+                current.push(c);
+                for c in chars.by_ref(){
+                    current.push(c);
+                    if c == '"'{
+                        break;
+                    }
+                }
+                tokens.push(Token { text: std::mem::take(&mut current), line, depth: 0 });
+                continue;
+            }
+            if c.is_whitespace() || matches!(c, '{' | '}' | '(' | ')'){
+                if !current.is_empty(){
+                    tokens.push(Token { text: std::mem::take(&mut current), line, depth: 0 });
+                }
+                if !c.is_whitespace(){
+                    tokens.push(Token { text: c.to_string(), line, depth: 0 });
+                }
+                continue;
+            }
+            current.push(c);
+        }
+        if !current.is_empty(){
+            tokens.push(Token { text: current, line, depth: 0 });
+        }
+    }
+    return tokens;
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text)
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()? as f64
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()? as f64
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse::<f64>().ok()?
+    } else {
+        return None;
+    };
+    return Some(if negative {-value} else {value});
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+    if digit.len() != 1{
+        return None;
+    }
+    return u8::from_str_radix(digit, 16).ok();
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    memory: Vec<u8>,
+    // where the ROM is loaded, the first byte of the output
+    origin: usize,
+    here: usize,
+    end: usize,
+    line: u32,
+    depth: u32,
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    flow: Vec<Flow>,
+    next_label: Option<String>,
+    symbols: SymbolTable
+}
+
+impl Assembler {
+    fn new(source: &str, origin: usize) -> Assembler {
+        return Assembler {
+            tokens: tokenize(source).into(),
+            memory: vec![0; ADDRESS_SPACE],
+            origin,
+            here: origin,
+            end: origin,
+            line: 1,
+            depth: 0,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            flow: Vec::new(),
+            next_label: None,
+            symbols: SymbolTable::default()
+        };
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, String> {
+        return Err(format!("line {}: {}", self.line, message));
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        match self.tokens.pop_front() {
+            Some(token) => {
+                self.line = token.line;
+                self.depth = token.depth;
+                return Ok(token.text);
+            }
+            None => return self.error("unexpected end of program".to_string())
+        }
+    }
+    fn peek(&self) -> Option<&str> {
+        return self.tokens.front().map(|t| t.text.as_str());
+    }
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != text{
+            return self.error(format!("expected '{}', found '{}'", text, token));
+        }
+        return Ok(());
+    }
+    fn name(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        if parse_number(&token).is_some() || parse_register(&token).is_some() || token.starts_with(':'){
+            return self.error(format!("'{}' is not a valid name", token));
+        }
+        return Ok(token);
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        return match self.as_register(&token) {
+            Some(reg) => Ok(reg),
+            None => self.error(format!("expected a register, found '{}'", token))
+        };
+    }
+    fn as_register(&self, token: &str) -> Option<u8> {
+        return parse_register(token).or_else(|| self.aliases.get(token).copied());
+    }
+
+    // a number, constant or already defined label
+    fn known_value(&self, token: &str) -> Option<i64> {
+        if let Some(value) = parse_number(token){
+            return Some(value.floor() as i64);
+        }
+        if let Some(value) = self.constants.get(token){
+            return Some(value.floor() as i64);
+        }
+        return self.labels.get(token).map(|addr| *addr as i64);
+    }
+    fn value(&mut self) -> Result<i64, String> {
+        let token = self.next()?;
+        return match self.known_value(&token) {
+            Some(value) => Ok(value),
+            None => self.error(format!("undefined name '{}'", token))
+        };
+    }
+    fn byte_value(&mut self) -> Result<u8, String> {
+        let value = self.value()?;
+        if !(-128..=255).contains(&value){
+            return self.error(format!("{} does not fit in a byte", value));
+        }
+        return Ok(value as u8);
+    }
+    fn nibble_value(&mut self) -> Result<u8, String> {
+        let value = self.value()?;
+        if !(0..=15).contains(&value){
+            return self.error(format!("{} does not fit in a nibble", value));
+        }
+        return Ok(value as u8);
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), String> {
+        if self.here >= ADDRESS_SPACE{
+            return self.error("program does not fit in memory".to_string());
+        }
+        self.memory[self.here] = byte;
+        self.here += 1;
+        self.end = self.end.max(self.here);
+        return Ok(());
+    }
+    fn emit(&mut self, op: u16) -> Result<usize, String> {
+        let addr = self.here;
+        if let Some(name) = self.next_label.take(){
+            self.define_label(name, addr + 1)?;
+        }
+        self.symbols.add_line(addr as u16, self.line);
+        self.emit_byte((op >> 8) as u8)?;
+        self.emit_byte(op as u8)?;
+        return Ok(addr);
+    }
+    // an instruction taking an address that may not be defined yet
+    fn emit_address(&mut self, op: u16) -> Result<(), String> {
+        let token = self.next()?;
+        let addr = self.emit(op)?;
+        match self.known_value(&token) {
+            Some(value) => self.patch(addr, Patch::Nnn, value)?,
+            None => self.fixups.push(Fixup { addr, patch: Patch::Nnn, name: token, line: self.line })
+        }
+        return Ok(());
+    }
+    fn patch(&mut self, addr: usize, patch: Patch, value: i64) -> Result<(), String> {
+        match patch {
+            Patch::Nnn => {
+                if !(0..=0xFFF).contains(&value){
+                    return self.error(format!("address {:X} is out of range", value));
+                }
+                self.memory[addr] |= (value >> 8) as u8;
+                self.memory[addr + 1] = value as u8;
+            }
+            Patch::Long => {
+                if !(0..=0xFFFF).contains(&value){
+                    return self.error(format!("address {:X} is out of range", value));
+                }
+                self.memory[addr + 2] = (value >> 8) as u8;
+                self.memory[addr + 3] = value as u8;
+            }
+            Patch::Unpack => {
+                if !(0..=0xFFF).contains(&value){
+                    return self.error(format!("address {:X} is out of range", value));
+                }
+                self.memory[addr + 1] |= (value >> 8) as u8;
+                self.memory[addr + 3] = value as u8;
+            }
+        }
+        return Ok(());
+    }
+    fn define_label(&mut self, name: String, addr: usize) -> Result<(), String> {
+        if self.labels.contains_key(&name) || self.constants.contains_key(&name){
+            return self.error(format!("'{}' is already defined", name));
+        }
+        self.symbols.add_label(&name, addr as u16);
+        self.labels.insert(name, addr);
+        return Ok(());
+    }
+
+    fn assemble(mut self) -> Result<OctoProgram, String> {
+        // execution starts at 0x200, so anything but `: main` first needs a jump
+        let starts_with_main = self.tokens.len() >= 2 && self.tokens[0].text == ":" && self.tokens[1].text == "main";
+        if !starts_with_main{
+            let addr = self.emit(0x1000)?;
+            self.fixups.push(Fixup { addr, patch: Patch::Nnn, name: "main".to_string(), line: 1 });
+        }
+        while !self.tokens.is_empty(){
+            let token = self.next()?;
+            self.statement(&token)?;
+        }
+        if let Some(flow) = self.flow.last(){
+            let open = match flow {
+                Flow::If(_) | Flow::Else(_) => "begin",
+                Flow::Loop(..) => "loop"
+            };
+            return self.error(format!("'{}' is never closed", open));
+        }
+        if let Some(name) = &self.next_label{
+            return self.error(format!(":next {} has no instruction after it", name));
+        }
+        for fixup in std::mem::take(&mut self.fixups){
+            self.line = fixup.line;
+            let value = match self.labels.get(&fixup.name) {
+                Some(addr) => *addr as i64,
+                None if fixup.name == "main" => return self.error("the program has no 'main' label".to_string()),
+                None => return self.error(format!("undefined name '{}'", fixup.name))
+            };
+            self.patch(fixup.addr, fixup.patch, value)?;
+        }
+        let rom = self.memory[self.origin..self.end].to_vec();
+        return Ok(OctoProgram { rom, symbols: self.symbols });
+    }
+
+    fn statement(&mut self, token: &str) -> Result<(), String> {
+        match token {
+            ":" => {
+                let name = self.name()?;
+                let here = self.here;
+                self.define_label(name, here)?;
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name, value as f64);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.register()?;
+                self.aliases.insert(name, reg);
+            }
+            ":calc" => {
+                let name = self.name()?;
+                let value = self.calc_block()?;
+                self.constants.insert(name, value);
+            }
+            ":byte" => {
+                let value = if self.peek() == Some("{") {self.calc_block()?.floor() as i64} else {self.value()?};
+                self.emit_byte(value as u8)?;
+            }
+            ":org" => {
+                let addr = self.value()?;
+                if !(self.origin as i64..ADDRESS_SPACE as i64).contains(&addr){
+                    return self.error(format!("address {:X} is out of range", addr));
+                }
+                self.here = addr as usize;
+            }
+            ":unpack" => {
+                let high = self.nibble_value()? as u16;
+                let token = self.next()?;
+                let addr = self.emit(0x6000 | (high << 4))?;
+                self.emit(0x6100)?;
+                match self.known_value(&token) {
+                    Some(value) => self.patch(addr, Patch::Unpack, value)?,
+                    None => self.fixups.push(Fixup { addr, patch: Patch::Unpack, name: token, line: self.line })
+                }
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.next_label = Some(name);
+            }
+            ":macro" => {
+                let name = self.name()?;
+                let mut args = Vec::new();
+                while self.peek() != Some("{"){
+                    args.push(self.name()?);
+                }
+                let body = self.block_tokens()?;
+                self.macros.insert(name, Macro { args, body });
+            }
+            ":breakpoint" => {
+                let name = self.name()?;
+                self.symbols.add_breakpoint(self.here as u16, &name);
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ":assert" => {
+                let message = match self.peek() {
+                    Some(text) if text.starts_with('"') => self.next()?.trim_matches('"').to_string(),
+                    _ => "assertion failed".to_string()
+                };
+                if self.calc_block()? == 0.0{
+                    return self.error(message);
+                }
+            }
+            ":call" => self.emit_address(0x2000)?,
+            ";" | "return" => {self.emit(0x00EE)?;}
+            "clear" => {self.emit(0x00E0)?;}
+            "scroll-right" => {self.emit(0x00FB)?;}
+            "scroll-left" => {self.emit(0x00FC)?;}
+            "exit" => {self.emit(0x00FD)?;}
+            "lores" => {self.emit(0x00FE)?;}
+            "hires" => {self.emit(0x00FF)?;}
+            "audio" => {self.emit(0xF002)?;}
+            "scroll-down" => {
+                let n = self.nibble_value()? as u16;
+                self.emit(0x00C0 | n)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble_value()? as u16;
+                self.emit(0x00D0 | n)?;
+            }
+            "jump" => self.emit_address(0x1000)?,
+            "jump0" => self.emit_address(0xB000)?,
+            "native" => self.emit_address(0x0000)?,
+            "bcd" => self.reg_op(0xF033)?,
+            "saveflags" => self.reg_op(0xF075)?,
+            "loadflags" => self.reg_op(0xF085)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                if self.peek() == Some("-"){
+                    // XO-CHIP range save/load
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    let op = if token == "save" {0x5002} else {0x5003};
+                    self.emit(op | (x << 8) | (y << 4))?;
+                }
+                else{
+                    let op = if token == "save" {0xF055} else {0xF065};
+                    self.emit(op | (x << 8))?;
+                }
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble_value()? as u16;
+                self.emit(0xD000 | (x << 8) | (y << 4) | n)?;
+            }
+            "plane" => {
+                let n = self.nibble_value()? as u16;
+                self.emit(0xF001 | (n << 8))?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let op = match token {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A
+                };
+                self.reg_op(op)?;
+            }
+            "i" => self.index_statement()?,
+            "if" => self.if_statement()?,
+            "else" => {
+                let jump = self.emit(0x1000)?;
+                match self.flow.pop() {
+                    Some(Flow::If(skip)) => {
+                        let here = self.here as i64;
+                        self.patch(skip, Patch::Nnn, here)?;
+                        self.flow.push(Flow::Else(jump));
+                    }
+                    _ => return self.error("'else' without 'begin'".to_string())
+                }
+            }
+            "end" => {
+                match self.flow.pop() {
+                    Some(Flow::If(addr)) | Some(Flow::Else(addr)) => {
+                        let here = self.here as i64;
+                        self.patch(addr, Patch::Nnn, here)?;
+                    }
+                    _ => return self.error("'end' without 'begin'".to_string())
+                }
+            }
+            "loop" => self.flow.push(Flow::Loop(self.here, Vec::new())),
+            "while" => {
+                let condition = self.condition()?;
+                for op in condition.prelude{
+                    self.emit(op)?;
+                }
+                self.emit(condition.skip_if_true)?;
+                let jump = self.emit(0x1000)?;
+                match self.flow.iter_mut().rev().find_map(|f| if let Flow::Loop(_, breaks) = f {Some(breaks)} else {None}) {
+                    Some(breaks) => breaks.push(jump),
+                    None => return self.error("'while' outside of a loop".to_string())
+                }
+            }
+            "again" => {
+                match self.flow.pop() {
+                    Some(Flow::Loop(start, breaks)) => {
+                        let jump = self.emit(0x1000)?;
+                        self.patch(jump, Patch::Nnn, start as i64)?;
+                        let here = self.here as i64;
+                        for addr in breaks{
+                            self.patch(addr, Patch::Nnn, here)?;
+                        }
+                    }
+                    _ => return self.error("'again' without 'loop'".to_string())
+                }
+            }
+            _ => self.other(token)?
+        }
+        return Ok(());
+    }
+
+    fn reg_op(&mut self, op: u16) -> Result<(), String> {
+        let x = self.register()? as u16;
+        self.emit(op | (x << 8))?;
+        return Ok(());
+    }
+
+    fn index_statement(&mut self) -> Result<(), String> {
+        let op = self.next()?;
+        match op.as_str() {
+            "+=" => self.reg_op(0xF01E)?,
+            ":=" => match self.peek() {
+                Some("hex") => {
+                    self.next()?;
+                    self.reg_op(0xF029)?;
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    self.reg_op(0xF030)?;
+                }
+                Some("long") => {
+                    self.next()?;
+                    let token = self.next()?;
+                    let addr = self.emit(0xF000)?;
+                    self.emit_byte(0)?;
+                    self.emit_byte(0)?;
+                    match self.known_value(&token) {
+                        Some(value) => self.patch(addr, Patch::Long, value)?,
+                        None => self.fixups.push(Fixup { addr, patch: Patch::Long, name: token, line: self.line })
+                    }
+                }
+                _ => self.emit_address(0xA000)?
+            },
+            _ => return self.error(format!("unknown operator 'i {}'", op))
+        }
+        return Ok(());
+    }
+
+    fn if_statement(&mut self) -> Result<(), String> {
+        let condition = self.condition()?;
+        for op in condition.prelude.iter(){
+            self.emit(*op)?;
+        }
+        let form = self.next()?;
+        match form.as_str() {
+            "then" => {
+                self.emit(condition.skip_if_false)?;
+                let token = self.next()?;
+                self.statement(&token)?;
+            }
+            "begin" => {
+                self.emit(condition.skip_if_true)?;
+                let jump = self.emit(0x1000)?;
+                self.flow.push(Flow::If(jump));
+            }
+            _ => return self.error(format!("expected 'then' or 'begin', found '{}'", form))
+        }
+        return Ok(());
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.register()? as u16;
+        let op = self.next()?;
+        let plain = |skip_if_true: u16, skip_if_false: u16| Condition { prelude: Vec::new(), skip_if_true, skip_if_false };
+        if op == "key"{
+            return Ok(plain(0xE09E | (x << 8), 0xE0A1 | (x << 8)));
+        }
+        if op == "-key"{
+            return Ok(plain(0xE0A1 | (x << 8), 0xE09E | (x << 8)));
+        }
+        let rhs = self.next()?;
+        let y = self.as_register(&rhs).map(|y| y as u16);
+        let n = match y {
+            Some(_) => 0,
+            None => match self.known_value(&rhs) {
+                Some(n) if (-128..=255).contains(&n) => (n as u8) as u16,
+                _ => return self.error(format!("expected a register or byte, found '{}'", rhs))
+            }
+        };
+        let eq = match y {
+            Some(y) => plain(0x5000 | (x << 8) | (y << 4), 0x9000 | (x << 8) | (y << 4)),
+            None => plain(0x3000 | (x << 8) | n, 0x4000 | (x << 8) | n)
+        };
+        match op.as_str() {
+            "==" => return Ok(eq),
+            "!=" => return Ok(plain(eq.skip_if_false, eq.skip_if_true)),
+            "<" | ">" | "<=" | ">=" => {}
+            _ => return self.error(format!("unknown comparison '{}'", op))
+        }
+        // Compute a subtraction in VF whose borrow flag answers the compare:
+        // VF ends up 1 exactly when the left side of the subtraction is >= the right
+        let (prelude, flag) = match (y, op.as_str()) {
+            // vx - vy
+            (Some(y), ">=") => (vec![0x8F00 | (x << 4), 0x8F05 | (y << 4)], 1),
+            (Some(y), "<") => (vec![0x8F00 | (x << 4), 0x8F05 | (y << 4)], 0),
+            // vy - vx
+            (Some(y), "<=") => (vec![0x8F00 | (x << 4), 0x8F07 | (y << 4)], 1),
+            (Some(y), _) => (vec![0x8F00 | (x << 4), 0x8F07 | (y << 4)], 0),
+            // vx - n
+            (None, ">=") => (vec![0x6F00 | n, 0x8F07 | (x << 4)], 1),
+            (None, "<") => (vec![0x6F00 | n, 0x8F07 | (x << 4)], 0),
+            // n - vx
+            (None, "<=") => (vec![0x6F00 | n, 0x8F05 | (x << 4)], 1),
+            (None, _) => (vec![0x6F00 | n, 0x8F05 | (x << 4)], 0)
+        };
+        return Ok(Condition { prelude, skip_if_true: 0x3F00 | flag, skip_if_false: 0x4F00 | flag });
+    }
+
+    // a statement starting with a register, macro, name or number
+    fn other(&mut self, token: &str) -> Result<(), String> {
+        if let Some(x) = self.as_register(token){
+            return self.register_statement(x as u16);
+        }
+        if let Some(mac) = self.macros.get(token).cloned(){
+            if self.depth >= MAX_MACRO_DEPTH{
+                return self.error(format!("macro {} expands more than {} levels deep", token, MAX_MACRO_DEPTH));
+            }
+            let mut args = HashMap::new();
+            for arg in mac.args.iter(){
+                let value = self.tokens.pop_front().ok_or(format!("line {}: macro {} is missing arguments", self.line, token))?;
+                args.insert(arg.clone(), value.text);
+            }
+            for body in mac.body.iter().rev(){
+                let text = args.get(&body.text).cloned().unwrap_or_else(|| body.text.clone());
+                self.tokens.push_front(Token { text, line: self.line, depth: self.depth + 1 });
+            }
+            return Ok(());
+        }
+        if self.labels.contains_key(token) || (self.known_value(token).is_none() && !token.starts_with(':')){
+            // a bare label is a call, possibly to one defined later
+            self.tokens.push_front(Token { text: token.to_string(), line: self.line, depth: self.depth });
+            return self.emit_address(0x2000);
+        }
+        if let Some(value) = self.known_value(token){
+            // a bare number or constant is a byte of data
+            return self.emit_byte(value as u8);
+        }
+        return self.error(format!("unknown directive '{}'", token));
+    }
+
+    fn register_statement(&mut self, x: u16) -> Result<(), String> {
+        let op = self.next()?;
+        let rhs = self.next()?;
+        let y = self.as_register(&rhs).map(|y| (y as u16) << 4);
+        let alu = |n: u16| y.map(|y| 0x8000 | (x << 8) | y | n);
+        let instr = match (op.as_str(), rhs.as_str()) {
+            (":=", "random") => {
+                let mask = self.byte_value()? as u16;
+                0xC000 | (x << 8) | mask
+            }
+            (":=", "delay") => 0xF007 | (x << 8),
+            (":=", "key") => 0xF00A | (x << 8),
+            (":=", _) if y.is_some() => alu(0).unwrap(),
+            ("|=", _) if y.is_some() => alu(1).unwrap(),
+            ("&=", _) if y.is_some() => alu(2).unwrap(),
+            ("^=", _) if y.is_some() => alu(3).unwrap(),
+            ("+=", _) if y.is_some() => alu(4).unwrap(),
+            ("-=", _) if y.is_some() => alu(5).unwrap(),
+            (">>=", _) if y.is_some() => alu(6).unwrap(),
+            ("=-", _) if y.is_some() => alu(7).unwrap(),
+            ("<<=", _) if y.is_some() => alu(0xE).unwrap(),
+            (":=", _) | ("+=", _) | ("-=", _) => {
+                let value = match self.known_value(&rhs) {
+                    Some(value) if (-128..=255).contains(&value) => value as u8,
+                    _ => return self.error(format!("expected a register or byte, found '{}'", rhs))
+                };
+                match op.as_str() {
+                    ":=" => 0x6000 | (x << 8) | value as u16,
+                    "+=" => 0x7000 | (x << 8) | value as u16,
+                    _ => 0x7000 | (x << 8) | value.wrapping_neg() as u16
+                }
+            }
+            _ => return self.error(format!("unknown operation 'v{:X} {} {}'", x, op, rhs))
+        };
+        self.emit(instr)?;
+        return Ok(());
+    }
+
+    // the tokens between a `{` and its matching `}`
+    fn block_tokens(&mut self) -> Result<Vec<Token>, String> {
+        self.expect("{")?;
+        let mut depth = 1;
+        let mut body = Vec::new();
+        loop {
+            let token = self.tokens.pop_front().ok_or(format!("line {}: '{{' is never closed", self.line))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0{
+                return Ok(body);
+            }
+            body.push(token);
+        }
+    }
+
+    // `{ expression }`. Like Octo, operators have no precedence and
+    // evaluate right to left unless parenthesized.
+    fn calc_block(&mut self) -> Result<f64, String> {
+        let tokens: Vec<String> = self.block_tokens()?.into_iter().map(|t| t.text).collect();
+        let mut pos = 0;
+        let value = self.calc_expr(&tokens, &mut pos)?;
+        if pos != tokens.len(){
+            return self.error(format!("unexpected '{}' in expression", tokens[pos]));
+        }
+        return Ok(value);
+    }
+    fn calc_expr(&self, tokens: &[String], pos: &mut usize) -> Result<f64, String> {
+        let lhs = self.calc_term(tokens, pos)?;
+        let Some(op) = tokens.get(*pos) else { return Ok(lhs) };
+        let apply: fn(f64, f64) -> f64 = match op.as_str() {
+            "+" => |a, b| a + b,
+            "-" => |a, b| a - b,
+            "*" => |a, b| a * b,
+            "/" => |a, b| a / b,
+            "%" => |a, b| a % b,
+            "&" => |a, b| ((a as i64) & (b as i64)) as f64,
+            "|" => |a, b| ((a as i64) | (b as i64)) as f64,
+            "^" => |a, b| ((a as i64) ^ (b as i64)) as f64,
+            "<<" => |a, b| ((a as i64) << (b as i64)) as f64,
+            ">>" => |a, b| ((a as i64) >> (b as i64)) as f64,
+            "pow" => f64::powf,
+            "min" => f64::min,
+            "max" => f64::max,
+            "<" => |a, b| (a < b) as u8 as f64,
+            "<=" => |a, b| (a <= b) as u8 as f64,
+            ">" => |a, b| (a > b) as u8 as f64,
+            ">=" => |a, b| (a >= b) as u8 as f64,
+            "==" => |a, b| (a == b) as u8 as f64,
+            "!=" => |a, b| (a != b) as u8 as f64,
+            ")" => return Ok(lhs),
+            _ => return self.error(format!("unknown operator '{}'", op))
+        };
+        *pos += 1;
+        let rhs = self.calc_expr(tokens, pos)?;
+        return Ok(apply(lhs, rhs));
+    }
+    fn calc_term(&self, tokens: &[String], pos: &mut usize) -> Result<f64, String> {
+        let Some(token) = tokens.get(*pos) else { return self.error("expression ends early".to_string()) };
+        *pos += 1;
+        if token == "("{
+            let value = self.calc_expr(tokens, pos)?;
+            if tokens.get(*pos).map(|t| t.as_str()) != Some(")"){
+                return self.error("missing ')'".to_string());
+            }
+            *pos += 1;
+            return Ok(value);
+        }
+        let unary: Option<fn(f64) -> f64> = match token.as_str() {
+            "-" => Some(|a| -a),
+            "~" => Some(|a| !(a as i64) as f64),
+            "!" => Some(|a| (a == 0.0) as u8 as f64),
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "exp" => Some(f64::exp),
+            "log" => Some(f64::ln),
+            "abs" => Some(f64::abs),
+            "sqrt" => Some(f64::sqrt),
+            "sign" => Some(f64::signum),
+            "ceil" => Some(f64::ceil),
+            "floor" => Some(f64::floor),
+            _ => None
+        };
+        if let Some(unary) = unary{
+            return Ok(unary(self.calc_term(tokens, pos)?));
+        }
+        if token == "@"{
+            // a byte already assembled
+            let addr = self.calc_term(tokens, pos)? as usize;
+            return Ok(*self.memory.get(addr).unwrap_or(&0) as f64);
+        }
+        return match token.as_str() {
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(std::f64::consts::PI),
+            "E" => Ok(std::f64::consts::E),
+            _ => match parse_number(token).or_else(|| self.constants.get(token.as_str()).copied()) {
+                Some(value) => Ok(value),
+                None => match self.labels.get(token.as_str()) {
+                    Some(addr) => Ok(*addr as f64),
+                    None => self.error(format!("undefined name '{}'", token))
+                }
+            }
+        };
+    }
+}
+
+// Compiles Octo source into a ROM image for START_OF_PROG
+pub fn assemble(source: &str) -> Result<OctoProgram, String> {
+    return assemble_at(source, START_OF_PROG);
+}
+
+// for programs loaded somewhere else, such as 0x600 on the ETI-660
+pub fn assemble_at(source: &str, load_address: usize) -> Result<OctoProgram, String> {
+    if load_address >= ADDRESS_SPACE{
+        return Err(format!("Load address {:X} is out of range", load_address));
+    }
+    return Assembler::new(source, load_address).assemble();
+}
+
+#[wasm_bindgen]
+pub fn assemble_octo(source: &str) -> Result<OctoProgram, String> {
+    return assemble(source);
+}
+
+#[wasm_bindgen]
+impl OctoCartridge {
+    pub fn assemble(&self) -> Result<OctoProgram, String> {
+        return assemble(&self.get_source());
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // Swaps in a new program, keeping quirks, seed and engine settings, so an
+    // edited source can be tried without rebuilding the emulator
+    pub fn load_rom(&mut self, rom: &[u8]){
+        self.rom = rom.to_vec();
+        self.rom_hash = sha1_smol::Sha1::from(rom).digest().to_string();
+        // symbols, breakpoints, cheats and patches belong to the old program
+        self.symbols = SymbolTable::new();
+        self.breakpoints.clear();
+        self.cheats.clear();
+        self.patches.clear();
+        self.reset();
+        self.clear_access_counts();
+        self.clear_profile();
+    }
+    // assembled for the current load address
+    pub fn load_octo(&mut self, source: &str) -> Result<SymbolTable, String> {
+        let program = assemble_at(source, self.load_address)?;
+        self.load_rom(&program.rom);
+        self.set_symbols(&program.symbols);
+        return Ok(program.symbols);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Chip8, MEM_SIZE};
+use crate::instructions::Instruction;
+use crate::variants::resolve_variant;
+
+use wasm_bindgen::prelude::*;
+
+
+pub(crate) type Handler = fn(&mut Chip8, Instruction);
+
+// An instruction decoded once together with the operation that executes it,
+// so a cache hit skips both the field extraction and the dispatch in `exec`
+#[derive(Clone)]
+pub(crate) struct CachedOp {
+    pub handler: Handler,
+    pub instr: Instruction
+}
+
+pub(crate) struct DecodeCache {
+    entries: Vec<Option<CachedOp>>,
+    pub enabled: bool
+}
+
+impl DecodeCache {
+    pub fn new() -> DecodeCache {
+        return DecodeCache { entries: vec![None; MEM_SIZE], enabled: true };
+    }
+    pub fn invalidate(&mut self, addr: usize){
+        // the byte is the low half of the instruction starting one before
+        // it, which for 0x000 is the one at 0xFFF
+        self.entries[addr] = None;
+        self.entries[(addr + MEM_SIZE - 1) % MEM_SIZE] = None;
+    }
+    pub fn clear(&mut self){
+        for entry in self.entries.iter_mut(){
+            *entry = None;
+        }
+    }
+}
+
+pub(crate) fn resolve(instr: &Instruction) -> Handler {
+    match instr.operation{
+        0x0 => match instr.nn {
+            0xE0 => Chip8::clear,
+            0xEE => Chip8::pop,
+            _ => Chip8::zero
+        },
+        0x1 => Chip8::jump,
+        0x2 => Chip8::push,
+        0x3 => Chip8::skip_if_eq,
+        0x4 => Chip8::skip_if_neq,
+        0x5 => Chip8::skip_eq_reg,
+        0x6 => Chip8::set_register,
+        0x7 => Chip8::add_register,
+        0x8 => match instr.n {
+            4 | 5 | 6 | 7 | 0xE => Chip8::stateful_arithmetic,
+            _ => Chip8::eight
+        },
+        0x9 => Chip8::skip_neq_reg,
+        0xA => Chip8::set_index,
+        0xB => Chip8::offset_jump,
+        0xC => Chip8::random,
+        0xD => Chip8::draw,
+        0xE => Chip8::skip_key,
+        _ => match instr.nn {
+            0x07 | 0x15 | 0x18 => Chip8::timers,
+            0x1E => Chip8::add_to_index,
+            0x0A => Chip8::get_key,
+            0x29 => Chip8::get_font,
+            0x33 => Chip8::decimal_conversion,
+            0x55 => Chip8::store,
+            0x65 => Chip8::load,
+            _ => Chip8::f
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn set_decode_cache(&mut self, enabled: bool){
+        self.decode_cache.enabled = enabled;
+        self.decode_cache.clear();
+    }
+    pub fn is_decode_cache_enabled(&self) -> bool {
+        return self.decode_cache.enabled;
+    }
+}
+
+impl Chip8 {
+    pub(crate) fn fetch_cached(&mut self) -> CachedOp {
+        if let Some(op) = &self.decode_cache.entries[self.pc]{
+            return op.clone();
+        }
+        let instr = self.fetch();
+        let handler = resolve_variant(self.platform, &instr).unwrap_or_else(|| resolve(&instr));
+        let op = CachedOp { handler, instr };
+        self.decode_cache.entries[self.pc] = Some(op.clone());
+        return op;
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+
+use wasm_bindgen::prelude::*;
+
+
+// Subroutine nesting each interpreter allows; CHIP-48 and SCHIP 1.0 have
+// the shallower stack
+pub const VIP_STACK_DEPTH: usize = 16;
+pub const CHIP48_STACK_DEPTH: usize = 12;
+pub const SCHIP_STACK_DEPTH: usize = 16;
+
+// One active subroutine call
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackFrame {
+    // address of the 2NNN
+    call_site: usize,
+    // where the call went, if the 2NNN is still in memory
+    callee: Option<usize>,
+    // where 00EE resumes
+    return_addr: usize
+}
+
+#[wasm_bindgen]
+impl StackFrame {
+    pub fn get_call_site(&self) -> usize {
+        return self.call_site;
+    }
+    pub fn get_callee(&self) -> Option<usize> {
+        return self.callee;
+    }
+    pub fn get_return_addr(&self) -> usize {
+        return self.return_addr;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_top_of_stack(&self) -> Option<usize> {
+        return self.stack.last().copied();
+    }
+    // outermost call first
+    pub fn get_call_stack(&self) -> Vec<StackFrame> {
+        return self.stack.iter().map(|&return_addr| {
+            let call_site = return_addr.wrapping_sub(2) % MEM_SIZE;
+            let opcode = u16::from_be_bytes([self.memory[call_site], self.memory[(call_site + 1) % MEM_SIZE]]);
+            let callee = if opcode >> 12 == 0x2 {Some((opcode & 0xFFF) as usize)} else {None};
+            return StackFrame { call_site, callee, return_addr };
+        }).collect();
+    }
+    pub fn call_stack_to_json(&self) -> String {
+        return serde_json::to_string(&self.get_call_stack()).expect("Call stack is always serializable");
+    }
+    pub fn get_stack_depth(&self) -> usize {
+        return self.stack.len();
+    }
+    pub fn get_stack_limit(&self) -> usize {
+        return self.stack_limit;
+    }
+    // A limit below the current depth keeps the calls already made, so the
+    // stack is left past its limit; that counts as an overflow
+    pub fn set_stack_limit(&mut self, depth: usize){
+        self.stack_limit = depth;
+        if self.stack.len() > depth{
+            self.stack_overflow = true;
+        }
+    }
+    // set once a call went past the limit, until the next reset
+    pub fn stack_overflowed(&self) -> bool {
+        return self.stack_overflow;
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+
+use wasm_bindgen::prelude::*;
+
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchKind {
+    // equal to the given value
+    Equal = 0,
+    // compared with the previous search
+    Changed = 1,
+    Unchanged = 2,
+    Increased = 3,
+    Decreased = 4
+}
+
+// Narrows memory down to the addresses holding a variable, one comparison
+// at a time, e.g. search for the lives count, lose a life, search for
+// Decreased
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemorySearch {
+    candidates: Vec<usize>,
+    // memory as of the last search
+    previous: Vec<u8>
+}
+
+#[wasm_bindgen]
+impl MemorySearch {
+    // every address is a candidate to begin with
+    pub fn new(chip8: &Chip8) -> MemorySearch {
+        return MemorySearch { candidates: (0..MEM_SIZE).collect(), previous: chip8.memory.to_vec() };
+    }
+    // `value` is only used by Equal
+    pub fn search(&mut self, chip8: &Chip8, kind: SearchKind, value: u8) -> usize {
+        let previous = &self.previous;
+        self.candidates.retain(|&addr| {
+            let (old, new) = (previous[addr], chip8.memory[addr]);
+            return match kind {
+                SearchKind::Equal => new == value,
+                SearchKind::Changed => new != old,
+                SearchKind::Unchanged => new == old,
+                SearchKind::Increased => new > old,
+                SearchKind::Decreased => new < old
+            };
+        });
+        self.previous = chip8.memory.to_vec();
+        return self.candidates.len();
+    }
+    pub fn get_candidates(&self) -> Vec<usize> {
+        return self.candidates.clone();
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheatTarget {
+    Memory = 0,
+    Register = 1
+}
+
+// Holds a memory address or register at a value
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheat {
+    name: String,
+    target: CheatTarget,
+    // address, or register number
+    addr: usize,
+    value: u8,
+    enabled: bool
+}
+
+#[wasm_bindgen]
+impl Cheat {
+    pub fn get_name(&self) -> String {
+        return self.name.clone();
+    }
+    pub fn get_target(&self) -> CheatTarget {
+        return self.target;
+    }
+    pub fn get_addr(&self) -> usize {
+        return self.addr;
+    }
+    pub fn get_value(&self) -> u8 {
+        return self.value;
+    }
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled;
+    }
+}
+
+// The cheats for one ROM, as saved and loaded
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheatList {
+    rom_sha1: String,
+    cheats: Vec<Cheat>
+}
+
+#[wasm_bindgen]
+impl CheatList {
+    pub fn get_rom_hash(&self) -> String {
+        return self.rom_sha1.clone();
+    }
+    pub fn get_cheats(&self) -> Vec<Cheat> {
+        return self.cheats.clone();
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Cheat list is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<CheatList, String> {
+        let list: CheatList = serde_json::from_str(json).map_err(|e| format!("Invalid cheat list: {}", e))?;
+        for cheat in list.cheats.iter(){
+            let limit = match cheat.target {
+                CheatTarget::Memory => MEM_SIZE,
+                CheatTarget::Register => 16
+            };
+            if cheat.addr >= limit{
+                return Err(format!("Cheat {} targets {:X}, which does not exist", cheat.name, cheat.addr));
+            }
+        }
+        return Ok(list);
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn add_memory_cheat(&mut self, name: &str, addr: usize, value: u8) -> usize {
+        return self.add_cheat(name, CheatTarget::Memory, addr % MEM_SIZE, value);
+    }
+    pub fn add_register_cheat(&mut self, name: &str, reg: usize, value: u8) -> usize {
+        return self.add_cheat(name, CheatTarget::Register, reg & 0xF, value);
+    }
+    pub fn remove_cheat(&mut self, idx: usize){
+        if idx < self.cheats.len(){
+            self.cheats.remove(idx);
+        }
+    }
+    pub fn set_cheat_enabled(&mut self, idx: usize, enabled: bool){
+        if let Some(cheat) = self.cheats.get_mut(idx){
+            cheat.enabled = enabled;
+        }
+    }
+    pub fn clear_cheats(&mut self){
+        self.cheats.clear();
+    }
+    pub fn get_cheats(&self) -> CheatList {
+        return CheatList { rom_sha1: self.rom_hash.clone(), cheats: self.cheats.clone() };
+    }
+    // replaces the current cheats
+    pub fn load_cheats(&mut self, list: &CheatList) -> Result<(), String> {
+        if list.rom_sha1 != self.rom_hash{
+            return Err(format!("Cheats belong to ROM {} but {} is loaded", list.rom_sha1, self.rom_hash));
+        }
+        self.cheats = list.cheats.clone();
+        return Ok(());
+    }
+}
+
+impl Chip8 {
+    fn add_cheat(&mut self, name: &str, target: CheatTarget, addr: usize, value: u8) -> usize {
+        self.cheats.push(Cheat { name: name.to_string(), target, addr, value, enabled: true });
+        return self.cheats.len() - 1;
+    }
+    // after every instruction, so the program never sees another value
+    pub(crate) fn apply_cheats(&mut self){
+        for i in 0..self.cheats.len(){
+            let Cheat { target, addr, value, enabled, .. } = self.cheats[i];
+            if !enabled{
+                continue;
+            }
+            match target {
+                CheatTarget::Memory => {
+                    // writing invalidates cached code, so only write changes
+                    if self.memory[addr] != value{
+                        self.write_memory(addr, value);
+                    }
+                }
+                CheatTarget::Register => self.gp_reg[addr] = value
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+
+use wasm_bindgen::prelude::*;
+
+
+// How often each byte of memory was executed, read or written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AccessCounts {
+    // counted at the first byte of each instruction
+    executed: Vec<u32>,
+    read: Vec<u32>,
+    written: Vec<u32>
+}
+
+impl AccessCounts {
+    fn new() -> AccessCounts {
+        return AccessCounts { executed: vec![0; MEM_SIZE], read: vec![0; MEM_SIZE], written: vec![0; MEM_SIZE] };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoveredInstruction {
+    pub addr: usize,
+    pub count: u32
+}
+
+// Which instructions of the loaded ROM ever ran
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    rom_sha1: String,
+    rom_len: usize,
+    instructions: Vec<CoveredInstruction>
+}
+
+#[wasm_bindgen]
+impl CoverageReport {
+    pub fn get_rom_hash(&self) -> String {
+        return self.rom_sha1.clone();
+    }
+    // addresses of every instruction executed at least once
+    pub fn get_executed(&self) -> Vec<usize> {
+        return self.instructions.iter().map(|i| i.addr).collect();
+    }
+    pub fn is_executed(&self, addr: usize) -> bool {
+        return self.instructions.iter().any(|i| i.addr == addr);
+    }
+    // share of the ROM's bytes that ran as code, data included in the total
+    pub fn get_ratio(&self) -> f64 {
+        if self.rom_len == 0{
+            return 0.0;
+        }
+        return (self.instructions.len() * 2).min(self.rom_len) as f64 / self.rom_len as f64;
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Coverage report is always serializable");
+    }
+}
+
+impl CoverageReport {
+    pub fn instructions(&self) -> &[CoveredInstruction] {
+        return &self.instructions;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // Counting makes `run` step one instruction at a time, as compiled
+    // blocks would bypass it. Counts survive `reset` so several runs of a
+    // test ROM add up.
+    pub fn set_access_tracking(&mut self, enabled: bool){
+        if enabled != self.access_counts.is_some(){
+            self.access_counts = if enabled {Some(Box::new(AccessCounts::new()))} else {None};
+        }
+    }
+    pub fn get_access_tracking(&self) -> bool {
+        return self.access_counts.is_some();
+    }
+    pub fn clear_access_counts(&mut self){
+        if let Some(counts) = self.access_counts.as_mut(){
+            **counts = AccessCounts::new();
+        }
+    }
+    // one count per address, empty while tracking is off
+    pub fn get_exec_counts(&self) -> Vec<u32> {
+        return self.access_counts.as_ref().map(|c| c.executed.clone()).unwrap_or_default();
+    }
+    pub fn get_read_counts(&self) -> Vec<u32> {
+        return self.access_counts.as_ref().map(|c| c.read.clone()).unwrap_or_default();
+    }
+    pub fn get_write_counts(&self) -> Vec<u32> {
+        return self.access_counts.as_ref().map(|c| c.written.clone()).unwrap_or_default();
+    }
+    pub fn coverage_report(&self) -> Option<CoverageReport> {
+        let counts = self.access_counts.as_ref()?;
+        let end = (self.load_address + self.rom.len()).min(MEM_SIZE);
+        let instructions = (self.load_address..end)
+            .filter(|&addr| counts.executed[addr] > 0)
+            .map(|addr| CoveredInstruction { addr, count: counts.executed[addr] })
+            .collect();
+        return Some(CoverageReport { rom_sha1: self.rom_hash.clone(), rom_len: self.rom.len(), instructions });
+    }
+}
+
+impl Chip8 {
+    pub(crate) fn count_exec(&mut self, addr: usize){
+        if let Some(counts) = self.access_counts.as_mut(){
+            counts.executed[addr % MEM_SIZE] = counts.executed[addr % MEM_SIZE].saturating_add(1);
+        }
+    }
+    pub(crate) fn count_read(&mut self, addr: usize){
+        if let Some(counts) = self.access_counts.as_mut(){
+            counts.read[addr % MEM_SIZE] = counts.read[addr % MEM_SIZE].saturating_add(1);
+        }
+    }
+    pub(crate) fn count_write(&mut self, addr: usize){
+        if let Some(counts) = self.access_counts.as_mut(){
+            counts.written[addr % MEM_SIZE] = counts.written[addr % MEM_SIZE].saturating_add(1);
+        }
+    }
+}
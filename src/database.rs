@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::Chip8;
+use crate::analyzer::Platform;
+use crate::keymap::Keymap;
+use crate::profile::RomProfile;
+use crate::quirks::Quirks;
+
+use wasm_bindgen::prelude::*;
+
+
+// programs.json in the format of the community chip-8-database
+const BUILTIN: &str = include_str!("../static/database/programs.json");
+
+// The database schema, field names as in chip-8-database
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Program {
+    pub title: String,
+    pub description: Option<String>,
+    pub release: Option<String>,
+    pub authors: Vec<String>,
+    pub roms: BTreeMap<String, RomEntry>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RomEntry {
+    pub file: Option<String>,
+    pub platforms: Vec<String>,
+    pub tickrate: Option<u32>,
+    pub quirky_platforms: BTreeMap<String, QuirkOverrides>,
+    pub keys: BTreeMap<String, u8>,
+    pub colors: Option<Colors>
+}
+
+// Only the quirks this emulator implements; `vblank` and
+// `memoryIncrementByX` are accepted and ignored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct QuirkOverrides {
+    pub shift: Option<bool>,
+    pub memory_leave_i_unchanged: Option<bool>,
+    pub wrap: Option<bool>,
+    pub jump: Option<bool>,
+    pub logic: Option<bool>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Colors {
+    pub pixels: Vec<String>,
+    pub buzzer: Option<String>,
+    pub silence: Option<String>
+}
+
+impl QuirkOverrides {
+    fn apply(&self, quirks: &mut Quirks){
+        // the database describes `shift` as "shift VX in place"
+        if let Some(shift) = self.shift{
+            quirks.shift_uses_vy = !shift;
+        }
+        if let Some(unchanged) = self.memory_leave_i_unchanged{
+            quirks.memory_increments_index = !unchanged;
+        }
+        if let Some(wrap) = self.wrap{
+            quirks.wrap_sprites = wrap;
+        }
+        if let Some(jump) = self.jump{
+            quirks.jump_uses_vx = jump;
+        }
+        if let Some(logic) = self.logic{
+            quirks.logic_resets_vf = logic;
+        }
+    }
+}
+
+// chip-8-database platform ids we can run, with the quirks each implies
+fn platform_for(id: &str) -> Option<(Platform, Quirks)> {
+    return match id {
+        "originalChip8" | "hybridVIP" => Some((Platform::Chip8, Quirks::vip())),
+        // shifts VY and advances I like the VIP, without its other quirks
+        "modernChip8" => Some((Platform::Chip8, Quirks { shift_uses_vy: true, memory_increments_index: true, ..Quirks::default() })),
+        "chip8x" => Some((Platform::Chip8X, Quirks::vip())),
+        "megachip8" => Some((Platform::MegaChip, Quirks::schip())),
+        "chip48" | "superchip1" | "superchip" => Some((Platform::SuperChip, Quirks::schip())),
+        "xochip" => Some((Platform::XoChip, Quirks::xochip())),
+        _ => None
+    };
+}
+
+// database key names for the keys a game uses, bound to the arrows, space
+// and the numpad for a second player
+fn key_code(name: &str) -> Option<(&'static str, Option<u32>)> {
+    return match name {
+        "up" => Some(("ArrowUp", Some(12))),
+        "down" => Some(("ArrowDown", Some(13))),
+        "left" => Some(("ArrowLeft", Some(14))),
+        "right" => Some(("ArrowRight", Some(15))),
+        "a" => Some(("Space", Some(0))),
+        "b" => Some(("ShiftLeft", Some(1))),
+        "player2Up" => Some(("Numpad8", None)),
+        "player2Down" => Some(("Numpad2", None)),
+        "player2Left" => Some(("Numpad4", None)),
+        "player2Right" => Some(("Numpad6", None)),
+        "player2A" => Some(("Numpad0", None)),
+        "player2B" => Some(("NumpadDecimal", None)),
+        _ => None
+    };
+}
+
+// Everything known about one ROM image, resolved to emulator settings
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomInfo {
+    title: String,
+    authors: Vec<String>,
+    description: Option<String>,
+    release: Option<String>,
+    platform: Platform,
+    quirks: Quirks,
+    tick_rate: Option<u32>,
+    keymap: Keymap,
+    colors: Option<Colors>
+}
+
+#[wasm_bindgen]
+impl RomInfo {
+    pub fn get_title(&self) -> String {
+        return self.title.clone();
+    }
+    pub fn get_authors(&self) -> Vec<String> {
+        return self.authors.clone();
+    }
+    pub fn get_description(&self) -> Option<String> {
+        return self.description.clone();
+    }
+    pub fn get_release(&self) -> Option<String> {
+        return self.release.clone();
+    }
+    pub fn get_platform(&self) -> Platform {
+        return self.platform;
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+    // instructions per frame
+    pub fn get_tick_rate(&self) -> Option<u32> {
+        return self.tick_rate;
+    }
+    // only the keys the game uses, to lay over a full layout
+    pub fn get_keymap(&self) -> Keymap {
+        return self.keymap.clone();
+    }
+    // background first, then one color per plane combination
+    pub fn get_pixel_colors(&self) -> Vec<String> {
+        return self.colors.as_ref().map(|c| c.pixels.clone()).unwrap_or_default();
+    }
+    pub fn get_buzzer_color(&self) -> Option<String> {
+        return self.colors.as_ref().and_then(|c| c.buzzer.clone());
+    }
+    pub fn get_silence_color(&self) -> Option<String> {
+        return self.colors.as_ref().and_then(|c| c.silence.clone());
+    }
+    // the settings a user starts from before making their own changes
+    pub fn profile(&self) -> RomProfile {
+        let mut profile = RomProfile::new();
+        profile.set_quirks(self.quirks);
+        profile.set_keymap(Keymap::standard().with_overrides(&self.keymap));
+        return profile;
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Rom info is always serializable");
+    }
+}
+
+impl RomInfo {
+    fn resolve(program: &Program, rom: &RomEntry) -> RomInfo {
+        let (mut platform, mut quirks) = (Platform::Chip8, Quirks::default());
+        if let Some((id, found)) = rom.platforms.iter().find_map(|id| platform_for(id).map(|p| (id, p))){
+            (platform, quirks) = found;
+            if let Some(overrides) = rom.quirky_platforms.get(id){
+                overrides.apply(&mut quirks);
+            }
+        }
+        let mut overrides = Keymap::empty();
+        for (name, idx) in rom.keys.iter(){
+            if let Some((code, button)) = key_code(name){
+                overrides.bind_key(code, *idx);
+                if let Some(button) = button{
+                    overrides.bind_gamepad_button(button, *idx);
+                }
+            }
+        }
+        return RomInfo {
+            title: program.title.clone(),
+            authors: program.authors.clone(),
+            description: program.description.clone(),
+            release: program.release.clone(),
+            platform,
+            quirks,
+            tick_rate: rom.tickrate,
+            keymap: overrides,
+            colors: rom.colors.clone()
+        };
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    programs: Vec<Program>,
+    // lowercase SHA-1 -> index into `programs`
+    hashes: HashMap<String, usize>
+}
+
+#[wasm_bindgen]
+impl RomDatabase {
+    pub fn builtin() -> RomDatabase {
+        return builtin().clone();
+    }
+    pub fn from_json(json: &str) -> Result<RomDatabase, String> {
+        let programs: Vec<Program> = serde_json::from_str(json).map_err(|e| format!("Invalid ROM database: {}", e))?;
+        let mut database = RomDatabase::default();
+        database.add(programs);
+        return Ok(database);
+    }
+    // entries in `json` take precedence over ones already present
+    pub fn merge_json(&mut self, json: &str) -> Result<(), String> {
+        let programs: Vec<Program> = serde_json::from_str(json).map_err(|e| format!("Invalid ROM database: {}", e))?;
+        self.add(programs);
+        return Ok(());
+    }
+    pub fn lookup(&self, sha1: &str) -> Option<RomInfo> {
+        let sha1 = sha1.to_ascii_lowercase();
+        let program = &self.programs[*self.hashes.get(&sha1)?];
+        return Some(RomInfo::resolve(program, &program.roms[&sha1]));
+    }
+    pub fn len(&self) -> usize {
+        return self.hashes.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.hashes.is_empty();
+    }
+}
+
+impl RomDatabase {
+    fn add(&mut self, programs: Vec<Program>){
+        for mut program in programs{
+            program.roms = program.roms.into_iter().map(|(sha1, rom)| (sha1.to_ascii_lowercase(), rom)).collect();
+            for sha1 in program.roms.keys(){
+                self.hashes.insert(sha1.clone(), self.programs.len());
+            }
+            self.programs.push(program);
+        }
+    }
+    pub fn programs(&self) -> &[Program] {
+        return &self.programs;
+    }
+}
+
+fn builtin() -> &'static RomDatabase {
+    static DATABASE: OnceLock<RomDatabase> = OnceLock::new();
+    return DATABASE.get_or_init(|| RomDatabase::from_json(BUILTIN).expect("Built in ROM database is valid"));
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_rom_info(&self) -> Option<RomInfo> {
+        return builtin().lookup(&self.rom_hash);
+    }
+    // applies the built in settings for the loaded ROM, if it is known
+    pub fn auto_configure(&mut self) -> Option<RomInfo> {
+        let info = self.get_rom_info()?;
+        self.set_quirks(info.quirks);
+        // SCHIP and XO-CHIP programs run on the classic opcodes, and a
+        // previous ROM's variant must not linger
+        let platform = if info.platform.has_own_opcodes() {info.platform} else {Platform::Chip8};
+        if platform != self.get_platform(){
+            self.set_platform(platform);
+        }
+        return Some(info);
+    }
+}
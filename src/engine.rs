@@ -0,0 +1,130 @@
+use std::rc::Rc;
+use crate::{Chip8, MEM_SIZE};
+use crate::cache::{resolve, CachedOp};
+use crate::instructions::Instruction;
+
+use wasm_bindgen::prelude::*;
+
+
+const MAX_BLOCK_LEN: usize = 64;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    // fetch, decode and dispatch every instruction through `exec`
+    Interpreter = 0,
+    // run basic blocks as pre-resolved handler lists (threaded code)
+    Threaded = 1
+}
+
+// Instructions after which execution may not simply fall through, or that
+// write memory and so may rewrite the block they are in
+fn ends_block(instr: &Instruction) -> bool {
+    match instr.operation {
+        0x0 => instr.nn == 0xEE,
+        0x1 | 0x2 | 0x3 | 0x4 | 0x5 | 0x9 | 0xB | 0xE => true,
+        0xF => matches!(instr.nn, 0x0A | 0x33 | 0x55),
+        _ => false
+    }
+}
+
+pub(crate) struct ThreadedCode {
+    // compiled block by start address
+    blocks: Vec<Option<Rc<[CachedOp]>>>,
+    // number of compiled blocks covering each byte
+    code_map: Vec<u8>,
+    count: usize
+}
+
+impl ThreadedCode {
+    pub fn new() -> ThreadedCode {
+        return ThreadedCode { blocks: vec![None; MEM_SIZE], code_map: vec![0; MEM_SIZE], count: 0 };
+    }
+    pub fn invalidate(&mut self, addr: usize){
+        if self.code_map[addr] == 0{
+            return;
+        }
+        // only blocks starting up to one maximal block before can reach addr
+        let first = addr.saturating_sub(MAX_BLOCK_LEN * 2 - 1);
+        for start in first..=addr{
+            let end = match &self.blocks[start] {
+                Some(ops) => start + ops.len() * 2,
+                None => continue
+            };
+            if addr < end{
+                self.blocks[start] = None;
+                self.count -= 1;
+                for count in self.code_map[start..end].iter_mut(){
+                    *count -= 1;
+                }
+            }
+        }
+    }
+    pub fn clear(&mut self){
+        for block in self.blocks.iter_mut(){
+            *block = None;
+        }
+        for count in self.code_map.iter_mut(){
+            *count = 0;
+        }
+        self.count = 0;
+    }
+    fn compile(&mut self, memory: &[u8], start: usize) -> Rc<[CachedOp]> {
+        let mut ops = Vec::new();
+        let mut addr = start;
+        while ops.len() < MAX_BLOCK_LEN && addr + 1 < memory.len(){
+            let instr = Instruction::from_raw(u16::from_be_bytes([memory[addr], memory[addr + 1]]));
+            let last = ends_block(&instr);
+            ops.push(CachedOp { handler: resolve(&instr), instr });
+            addr += 2;
+            if last{
+                break;
+            }
+        }
+        for count in self.code_map[start..addr].iter_mut(){
+            *count += 1;
+        }
+        let block: Rc<[CachedOp]> = ops.into();
+        self.blocks[start] = Some(block.clone());
+        self.count += 1;
+        return block;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn set_engine(&mut self, engine: Engine){
+        self.engine = engine;
+        self.threaded.clear();
+    }
+    pub fn get_engine(&self) -> Engine {
+        return self.engine;
+    }
+    pub fn threaded_block_count(&self) -> usize {
+        return self.threaded.count;
+    }
+}
+
+impl Chip8 {
+    // Runs the basic block at pc if it fits in `budget` instructions and
+    // returns how many instructions it covered
+    pub(crate) fn run_threaded_block(&mut self, budget: usize) -> Option<usize> {
+        if self.pc + 1 >= MEM_SIZE{
+            return None;
+        }
+        let block = match &self.threaded.blocks[self.pc] {
+            Some(block) => block.clone(),
+            None => self.threaded.compile(&self.memory, self.pc)
+        };
+        if block.len() > budget{
+            return None;
+        }
+        for op in block.iter(){
+            self.poll_input();
+            self.cycles += 1;
+            self.pc += 2;
+            (op.handler)(self, op.instr.clone());
+        }
+        return Some(block.len());
+    }
+}
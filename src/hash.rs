@@ -0,0 +1,136 @@
+use crate::{Chip8, PixelState, MEM_SIZE};
+use crate::analyzer::Platform;
+use crate::megachip::MegaChip;
+use crate::variants::VariantState;
+
+use wasm_bindgen::prelude::*;
+
+
+const FNV_OFFSET: u64 = 0xCBF29CE484222325;
+const FNV_PRIME: u64 = 0x100000001B3;
+const DISPLAY_DOMAIN: u64 = 1 << 40;
+
+// splitmix64 finalizer
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+// Memory and display are hashed as the XOR of one mixed value per cell, so a
+// single write can be folded in without touching the rest.
+pub(crate) fn memory_cell_hash(addr: usize, val: u8) -> u64 {
+    return mix(((addr as u64) << 8) | val as u64);
+}
+pub(crate) fn pixel_hash(idx: usize, pixel: PixelState) -> u64 {
+    return mix(DISPLAY_DOMAIN | ((idx as u64) << 1) | pixel as u64);
+}
+pub(crate) fn memory_hash(memory: &[u8]) -> u64 {
+    return memory.iter().enumerate().fold(0, |acc, (addr, val)| acc ^ memory_cell_hash(addr, *val));
+}
+pub(crate) fn display_hash(display: &[PixelState]) -> u64 {
+    return display.iter().enumerate().fold(0, |acc, (idx, pixel)| acc ^ pixel_hash(idx, *pixel));
+}
+
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn write(&mut self, bytes: &[u8]){
+        for byte in bytes{
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+    fn write_usize(&mut self, val: usize){
+        self.write(&(val as u64).to_le_bytes());
+    }
+}
+
+pub(crate) struct HashParts<'a> {
+    pub pc: usize,
+    pub index: usize,
+    pub stack: &'a [usize],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub gp_reg: &'a [u8; 16],
+    pub memory_hash: u64,
+    pub display_hash: u64,
+    pub platform: Platform,
+    pub variant: &'a VariantState,
+    pub mega: Option<&'a MegaChip>
+}
+
+pub(crate) fn combine(parts: HashParts) -> u64 {
+    let mut fnv = Fnv(FNV_OFFSET);
+    fnv.write_usize(parts.pc);
+    fnv.write_usize(parts.index);
+    fnv.write_usize(parts.stack.len());
+    for addr in parts.stack{
+        fnv.write_usize(*addr);
+    }
+    fnv.write(&[parts.delay_timer, parts.sound_timer]);
+    fnv.write(parts.gp_reg);
+    fnv.write(&parts.memory_hash.to_le_bytes());
+    fnv.write(&parts.display_hash.to_le_bytes());
+    fnv.write(&[parts.platform as u8]);
+    parts.variant.hash_into(&mut fnv);
+    if let Some(mega) = parts.mega{
+        mega.hash_into(&mut fnv);
+    }
+    return mix(fnv.0);
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // Hash of registers, index, pc, stack, timers, memory, display and the
+    // state of the platform's own opcodes.
+    // Cycle/frame counters and the RNG are left out so identical machine
+    // states hash the same no matter how they were reached.
+    pub fn state_hash(&self) -> u64 {
+        return self.hash_with(memory_hash(&self.memory), display_hash(&self.display));
+    }
+    // Same value as `state_hash` but uses the memory and display hashes that
+    // are kept up to date on every write instead of rescanning them
+    pub fn incremental_state_hash(&self) -> u64 {
+        return self.hash_with(self.mem_hash, self.display_hash);
+    }
+}
+
+impl Chip8 {
+    fn hash_with(&self, memory_hash: u64, display_hash: u64) -> u64 {
+        return combine(HashParts {
+            pc: self.pc,
+            index: self.index,
+            stack: &self.stack,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            gp_reg: &self.gp_reg,
+            memory_hash,
+            display_hash,
+            platform: self.platform,
+            variant: &self.variant,
+            mega: self.mega.as_deref()
+        });
+    }
+    pub(crate) fn rehash(&mut self){
+        self.mem_hash = memory_hash(&self.memory);
+        self.display_hash = display_hash(&self.display);
+    }
+    pub(crate) fn write_memory(&mut self, addr: usize, val: u8){
+        // I can point anywhere, the address space wraps like on the VIP
+        let addr = addr % MEM_SIZE;
+        let old = self.memory[addr];
+        self.memory[addr] = val;
+        self.mem_hash ^= memory_cell_hash(addr, old) ^ memory_cell_hash(addr, val);
+        self.decode_cache.invalidate(addr);
+        self.threaded.invalidate(addr);
+        if let Some(jit) = self.jit.as_mut(){
+            jit.invalidate(addr);
+        }
+    }
+    pub(crate) fn set_pixel(&mut self, idx: usize, pixel: PixelState){
+        let old = self.display[idx];
+        self.display[idx] = pixel;
+        self.display_hash ^= pixel_hash(idx, old) ^ pixel_hash(idx, pixel);
+    }
+}
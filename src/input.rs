@@ -0,0 +1,60 @@
+use crate::{Chip8, KeyState};
+
+use wasm_bindgen::prelude::*;
+
+
+// A keypad change reported by the frontend. `timestamp` is the event time in
+// milliseconds (e.g. `KeyboardEvent.timeStamp`) and only used for ordering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEvent {
+    pub key: u8,
+    pub state: KeyState,
+    pub timestamp: f64
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn queue_key_event(&mut self, idx: usize, state: KeyState, timestamp: f64){
+        if self.playback.is_some(){
+            // a movie owns the keypad while it plays
+            return;
+        }
+        let event = KeyEvent { key: (idx & 0xF) as u8, state, timestamp };
+        // keep the queue ordered in case listeners report out of order
+        let pos = self.input_queue.iter()
+            .position(|e| e.timestamp > timestamp)
+            .unwrap_or(self.input_queue.len());
+        self.input_queue.insert(pos, event);
+    }
+    pub fn pending_key_events(&self) -> usize {
+        return self.input_queue.len();
+    }
+    pub fn clear_key_events(&mut self){
+        self.input_queue.clear();
+    }
+}
+
+impl Chip8 {
+    pub(crate) fn poll_input(&mut self){
+        if self.playback.is_some(){
+            self.poll_playback();
+            return;
+        }
+        // at most one event per instruction so a press and release queued
+        // within the same frame are both observed by the program
+        if let Some(event) = self.input_queue.pop_front(){
+            self.apply_key(event.key, event.state);
+        }
+    }
+    pub(crate) fn apply_key(&mut self, key: u8, state: KeyState){
+        let key = key & 0xF;
+        if self.keypad[key as usize] == state{
+            return;
+        }
+        self.keypad[key as usize] = state;
+        let (frame, cycle) = (self.frame, self.cycles);
+        if let Some(movie) = self.recording.as_mut(){
+            movie.record(frame, cycle, key, state);
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub struct Instruction{
 }
 
 #[wasm_bindgen]
+#[allow(clippy::inherent_to_string, clippy::should_implement_trait)]
 impl Instruction {
     pub fn to_string(& self) -> String{
         return format!("op={op:2X} x={x:2X} y={y:2X} n={n:2X} nn={nn:2X} nnn={nnn:03X}",
@@ -24,15 +25,25 @@ impl Instruction {
             nnn=self.nnn
         )
     }
+    pub fn from_raw(raw: u16) -> Instruction {
+        return Instruction { 
+            operation: ((raw >> 12) & 0xF) as u8,
+            x: ((raw >> 8) & 0xF) as u8,
+            y: ((raw >> 4) & 0xF) as u8,
+            n: (raw & 0xF) as u8,
+            nn: (raw & 0xFF) as u8,
+            nnn: raw & 0xFFF
+        }
+    }
     pub fn from_str(val: &str) -> Instruction {
-        let mut digits = val.chars();
+        let digits = val.chars();
         let mut u8s: [u8;4] = [0;4];
         for (i,c) in digits.enumerate(){
             u8s[i] = char::to_digit(c, 16).unwrap() as u8;
         }
         let nn = u8::from_str_radix(&val[2..4], 16).expect("Cant parse string");
         let nnn = u16::from_str_radix(&val[1..4], 16).expect("Cant parse string");
-        return Instruction { operation: u8s[0], x: u8s[1], y: u8s[2], n: u8s[3], nn: nn, nnn: nnn }
+        return Instruction { operation: u8s[0], x: u8s[1], y: u8s[2], n: u8s[3], nn, nnn }
 
     }
 }
\ No newline at end of file
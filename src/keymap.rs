@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use wasm_bindgen::prelude::*;
+
+
+// COSMAC VIP hex keypad laid over the left side of a QWERTY keyboard
+//  1 2 3 C      1 2 3 4
+//  4 5 6 D  ->  Q W E R
+//  7 8 9 E      A S D F
+//  A 0 B F      Z X C V
+const STANDARD_LAYOUT: [(&str, u8); 16] = [
+    ("Digit1", 0x1), ("Digit2", 0x2), ("Digit3", 0x3), ("Digit4", 0xC),
+    ("KeyQ", 0x4), ("KeyW", 0x5), ("KeyE", 0x6), ("KeyR", 0xD),
+    ("KeyA", 0x7), ("KeyS", 0x8), ("KeyD", 0x9), ("KeyF", 0xE),
+    ("KeyZ", 0xA), ("KeyX", 0x0), ("KeyC", 0xB), ("KeyV", 0xF)
+];
+// the original layout of this site: keys in hex order along the rows
+const LEGACY_LAYOUT: [(&str, u8); 16] = [
+    ("KeyQ", 0x0), ("KeyW", 0x1), ("KeyE", 0x2), ("KeyR", 0x3), ("KeyT", 0x4),
+    ("KeyA", 0x5), ("KeyS", 0x6), ("KeyD", 0x7), ("KeyF", 0x8), ("KeyG", 0x9),
+    ("KeyZ", 0xA), ("KeyX", 0xB), ("KeyC", 0xC), ("KeyV", 0xD), ("KeyB", 0xE),
+    ("Space", 0xF)
+];
+// Octo's extra bindings on top of the standard layout: arrows on the 5/7/8/9
+// cross and space on 6
+const OCTO_EXTRAS: [(&str, u8); 5] = [
+    ("ArrowUp", 0x5), ("ArrowLeft", 0x7), ("ArrowDown", 0x8), ("ArrowRight", 0x9), ("Space", 0x6)
+];
+// Gamepad API "standard" mapping button indices. The d-pad lands on the
+// 2/4/6/8 cross most ROMs use for movement.
+const GAMEPAD_LAYOUT: [(u32, u8); 10] = [
+    (12, 0x2), (13, 0x8), (14, 0x4), (15, 0x6),
+    (0, 0x5), (1, 0x0), (2, 0xA), (3, 0xB),
+    (8, 0xE), (9, 0xF)
+];
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Keymap {
+    // KeyboardEvent.code -> keypad index
+    keys: BTreeMap<String, u8>,
+    // Gamepad button index -> keypad index
+    buttons: BTreeMap<u32, u8>
+}
+
+#[wasm_bindgen]
+impl Keymap {
+    pub fn empty() -> Keymap {
+        return Keymap::default();
+    }
+    pub fn standard() -> Keymap {
+        return Keymap::from_layout(&STANDARD_LAYOUT);
+    }
+    pub fn legacy() -> Keymap {
+        return Keymap::from_layout(&LEGACY_LAYOUT);
+    }
+    pub fn octo() -> Keymap {
+        let mut keymap = Keymap::standard();
+        for (code, idx) in OCTO_EXTRAS{
+            keymap.bind_key(code, idx);
+        }
+        return keymap;
+    }
+    pub fn preset(name: &str) -> Option<Keymap> {
+        match name {
+            "standard" => Some(Keymap::standard()),
+            "legacy" => Some(Keymap::legacy()),
+            "octo" => Some(Keymap::octo()),
+            _ => None
+        }
+    }
+
+    pub fn map_key(&self, code: &str) -> Option<u8> {
+        return self.keys.get(code).copied();
+    }
+    pub fn bind_key(&mut self, code: &str, idx: u8){
+        self.keys.insert(code.to_string(), idx & 0xF);
+    }
+    pub fn unbind_key(&mut self, code: &str){
+        self.keys.remove(code);
+    }
+
+    pub fn map_gamepad_button(&self, button: u32) -> Option<u8> {
+        return self.buttons.get(&button).copied();
+    }
+    pub fn bind_gamepad_button(&mut self, button: u32, idx: u8){
+        self.buttons.insert(button, idx & 0xF);
+    }
+    pub fn unbind_gamepad_button(&mut self, button: u32){
+        self.buttons.remove(&button);
+    }
+
+    // bindings in `overrides` replace ours, everything else is kept
+    pub fn with_overrides(&self, overrides: &Keymap) -> Keymap {
+        let mut merged = self.clone();
+        for (code, idx) in overrides.keys.iter(){
+            merged.keys.insert(code.clone(), *idx);
+        }
+        for (button, idx) in overrides.buttons.iter(){
+            merged.buttons.insert(*button, *idx);
+        }
+        return merged;
+    }
+}
+
+impl Keymap {
+    fn from_layout(layout: &[(&str, u8)]) -> Keymap {
+        let mut keymap = Keymap::default();
+        for (code, idx) in layout{
+            keymap.bind_key(code, *idx);
+        }
+        for (button, idx) in GAMEPAD_LAYOUT{
+            keymap.bind_gamepad_button(button, idx);
+        }
+        return keymap;
+    }
+}
@@ -1,27 +1,77 @@
+#![allow(clippy::needless_return)]
 pub mod instructions;
 pub mod fonts;
+pub mod quirks;
+pub mod input;
+pub mod keymap;
+pub mod profile;
+pub mod movie;
+pub mod state;
+pub mod netplay;
+pub mod hash;
+mod cache;
+pub mod recompiler;
+pub mod engine;
+pub mod analyzer;
+pub mod database;
+pub mod octo;
+pub mod symbols;
+pub mod assembler;
+pub mod callstack;
+pub mod memory;
+pub mod coverage;
+pub mod profiler;
+pub mod cheats;
+pub mod patch;
+pub mod megachip;
+mod variants;
 mod operations;
 use instructions::Instruction;
 use fonts::{get_font_val, FONTS_SIZE,FONT_OFFSET};
+use quirks::Quirks;
+use input::KeyEvent;
+use movie::{Movie, Playback};
+use cache::DecodeCache;
+use recompiler::Jit;
+use engine::{Engine, ThreadedCode};
+use symbols::SymbolTable;
+use callstack::VIP_STACK_DEPTH;
+use coverage::AccessCounts;
+use profiler::Profiler;
+use cheats::Cheat;
+use patch::RomPatch;
+use analyzer::Platform;
+use variants::{resolve_variant, VariantState};
+use megachip::MegaChip;
+use std::collections::{BTreeSet, VecDeque};
+use serde::{Deserialize, Serialize};
 
 use wasm_bindgen::prelude::*;
-use js_sys;
 
 
 const MEM_SIZE: usize = 4096;
 const DISPLAY_WIDTH: usize = 64;
 const DISPLAY_HEIGHT: usize = 32;
-const PIXELS: usize = DISPLAY_HEIGHT * DISPLAY_WIDTH;
+// two page HIRES programs use 64 rows, everything else leaves the bottom half dark
+const HIRES_HEIGHT: usize = 64;
+const PIXELS: usize = HIRES_HEIGHT * DISPLAY_WIDTH;
 const START_OF_PROG: usize = 0x200;
 
 
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     pub fn alert(s: &str);
     #[wasm_bindgen(js_namespace= console)]
     pub fn log(s: &str);
 }
+// native builds (tests, benches, tools) have no console to import
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(s: &str) {
+    eprintln!("{}", s);
+}
+#[allow(unused_macros)]
 macro_rules! console_log {
     // Note that this is using the `log` function imported above during
     // `bare_bones`
@@ -30,7 +80,7 @@ macro_rules! console_log {
 
 #[wasm_bindgen]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PixelState{
     ON = 1,
     OFF = 0
@@ -38,7 +88,7 @@ pub enum PixelState{
 
 #[wasm_bindgen]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum KeyState{
     ON = 1,
     OFF = 0
@@ -49,13 +99,46 @@ pub enum KeyState{
 pub struct Chip8 {
     pc: usize,
     index: usize,
+    // where the ROM is copied and where execution starts
+    load_address: usize,
+    entry_point: usize,
     pub delay_timer: u8,
     pub sound_timer: u8,
     stack: Vec<usize>,
+    stack_limit: usize,
+    stack_overflow: bool,
     display: [PixelState; PIXELS],
     memory: [u8; MEM_SIZE],
     gp_reg: [u8; 16],
-    keypad: [KeyState; 16]
+    keypad: [KeyState; 16],
+    input_queue: VecDeque<KeyEvent>,
+    key_wait: Option<u8>,
+    quirks: Quirks,
+    rom: Vec<u8>,
+    rom_hash: String,
+    seed: u64,
+    rng: u64,
+    cycles: u64,
+    frame: u64,
+    recording: Option<Movie>,
+    playback: Option<Playback>,
+    mem_hash: u64,
+    display_hash: u64,
+    decode_cache: DecodeCache,
+    jit: Option<Jit>,
+    engine: Engine,
+    threaded: ThreadedCode,
+    symbols: SymbolTable,
+    breakpoints: BTreeSet<usize>,
+    // pc of the breakpoint that ended the last `run`
+    break_hit: Option<usize>,
+    access_counts: Option<Box<AccessCounts>>,
+    profiler: Option<Box<Profiler>>,
+    cheats: Vec<Cheat>,
+    patches: Vec<RomPatch>,
+    platform: Platform,
+    variant: VariantState,
+    mega: Option<Box<MegaChip>>
 }
 
 
@@ -63,29 +146,16 @@ pub struct Chip8 {
 impl Chip8 {
     pub fn new(rom: &js_sys::Uint8Array) -> Chip8 {
         console_error_panic_hook::set_once();
-        let mut mem:[u8;MEM_SIZE] = [0; MEM_SIZE];
         let mut slice: Vec<u8> = vec![0; rom.length() as usize];
         rom.copy_to(&mut slice[..]);
-        for (i, val) in slice.iter().enumerate(){
-            mem[i + START_OF_PROG] = val.clone() as u8;
-        }// load program
-        for i in 0..FONTS_SIZE{
-            mem[i + FONT_OFFSET] = get_font_val(i);
-        }// load fonts
-        return Chip8 {
-            pc: START_OF_PROG,
-            index: 0,
-            delay_timer: 0,
-            sound_timer: 0,
-            stack: Vec::new(),
-            display: [PixelState::OFF; PIXELS],
-            memory: mem,
-            gp_reg: [0; 16],
-            keypad: [KeyState::OFF; 16]
-        }
+        return Chip8::from_bytes(&slice);
     }
 
     fn exec(&mut self, instr: Instruction){
+        if let Some(handler) = resolve_variant(self.platform, &instr){
+            handler(self, instr);
+            return;
+        }
         match instr.operation{
             0x0 => self.zero(instr), 
             0x1 => self.jump(instr),
@@ -109,38 +179,131 @@ impl Chip8 {
 
     fn fetch(&mut self) -> Instruction{
         let upper_byte = self.memory[self.pc];
-        let lower_byte = self.memory[self.pc + 1];
+        let lower_byte = self.memory[(self.pc + 1) % MEM_SIZE];
         let raw = u16::from_be_bytes([upper_byte, lower_byte]);
-        return Instruction { 
-            operation: ((raw >> 12) & 0xF) as u8,
-            x: ((raw >> 8) & 0xF) as u8,
-            y: ((raw >> 4) & 0xF) as u8,
-            n: (raw & 0xF) as u8,
-            nn: (raw & 0xFF) as u8,
-            nnn: (raw & 0xFFF) as u16
-        }
+        return Instruction::from_raw(raw);
     }
     pub fn get_display(&self) -> *const PixelState {
         return self.display.as_ptr();
     }
     pub fn tick(&mut self){ 
-        let instr = self.fetch();
-        self.pc += 2;
-        let instr_debug = instr.to_string();
-        // console_log!("Instruction {}",instr_debug);
-        self.exec(instr);
+        self.pc %= MEM_SIZE;
+        self.poll_input();
+        self.cycles += 1;
+        self.count_exec(self.pc);
+        if self.decode_cache.enabled{
+            let op = self.fetch_cached();
+            self.profile_instruction(self.pc, &op.instr);
+            self.pc += 2;
+            (op.handler)(self, op.instr);
+        }
+        else{
+            let instr = self.fetch();
+            self.profile_instruction(self.pc, &instr);
+            self.pc += 2;
+            // console_log!("Instruction {}",instr.to_string());
+            self.exec(instr);
+        }
+        self.apply_cheats();
     }
     pub fn tick_timers(&mut self){
         self.delay_timer = self.delay_timer.saturating_sub(1);
         self.sound_timer = self.sound_timer.saturating_sub(1);
+        self.frame += 1;
+        self.end_finished_playback();
+    }
+    pub fn run(&mut self, cycles: u32){
+        let mut remaining = cycles as usize;
+        self.break_hit = None;
+        while remaining > 0{
+            // a run that starts on a breakpoint executes it, which is how a
+            // paused program continues
+            if remaining < cycles as usize && self.breakpoints.contains(&(self.pc % MEM_SIZE)){
+                self.break_hit = Some(self.pc % MEM_SIZE);
+                return;
+            }
+            if !self.single_stepping(){
+                if let Some(len) = self.run_compiled_block(remaining){
+                    remaining -= len;
+                    continue;
+                }
+                if self.engine == Engine::Threaded{
+                    if let Some(len) = self.run_threaded_block(remaining){
+                        remaining -= len;
+                        continue;
+                    }
+                }
+            }
+            self.tick();
+            remaining -= 1;
+        }
+    }
+    pub fn run_frame(&mut self, cycles: u32){
+        self.run(cycles);
+        self.tick_timers();
+    }
+    pub fn reset(&mut self){
+        // patches were checked when added, so one that fails now is dropped
+        // whole rather than leaving the ROM half patched
+        let mut image = self.rom.clone();
+        let (rom_hash, load_address) = (&self.rom_hash, self.load_address);
+        self.patches.retain(|p| match patch::patched(&image, rom_hash, load_address, std::slice::from_ref(p)) {
+            Ok(next) => {
+                image = next;
+                return true;
+            }
+            Err(e) => {
+                console_log!("Dropping a patch that no longer applies: {}", e);
+                return false;
+            }
+        });
+        let mut fresh = Chip8::with_layout(&image, self.load_address, self.entry_point);
+        fresh.rom = std::mem::take(&mut self.rom);
+        fresh.rom_hash = std::mem::take(&mut self.rom_hash);
+        fresh.patches = std::mem::take(&mut self.patches);
+        fresh.quirks = self.quirks;
+        fresh.decode_cache.enabled = self.decode_cache.enabled;
+        fresh.engine = self.engine;
+        fresh.stack_limit = self.stack_limit;
+        fresh.platform = self.platform;
+        if self.platform == Platform::MegaChip{
+            fresh.mega = Some(Box::new(MegaChip::new(&image, self.load_address)));
+        }
+        fresh.jit = self.jit.take();
+        if let Some(jit) = fresh.jit.as_mut(){
+            jit.clear();
+        }
+        fresh.set_seed(self.seed);
+        fresh.symbols = std::mem::take(&mut self.symbols);
+        fresh.breakpoints = std::mem::take(&mut self.breakpoints);
+        fresh.access_counts = self.access_counts.take();
+        fresh.profiler = self.profiler.take();
+        if let Some(profiler) = fresh.profiler.as_mut(){
+            profiler.forget_calls();
+        }
+        fresh.cheats = std::mem::take(&mut self.cheats);
+        *self = fresh;
+    }
+    pub fn get_cycles(&self) -> u64 {
+        return self.cycles;
+    }
+    pub fn get_frame(&self) -> u64 {
+        return self.frame;
+    }
+    pub fn get_rom_hash(&self) -> String {
+        return self.rom_hash.clone();
+    }
+    pub fn get_seed(&self) -> u64 {
+        return self.seed;
+    }
+    pub fn set_seed(&mut self, seed: u64){
+        self.seed = seed;
+        self.rng = seed;
     }
 
     pub fn get_pc(&self) -> usize{
         return self.pc;
     }
-    pub fn get_top_of_stack(&self) -> usize {
-        return self.stack[0];
-    }
     pub fn get_register(&self, idx: usize) -> u8 {
         return self.gp_reg[idx];
     }
@@ -152,9 +315,97 @@ impl Chip8 {
 
     }
     pub fn set_key_state(&mut self, idx: usize, state: KeyState){
-        self.keypad[idx] = state;
+        // writes the keypad directly, bypassing the input queue
+        self.apply_key(idx as u8, state);
     }
     pub fn get_key_state(&mut self, idx: usize) -> KeyState{
         return self.keypad[idx];
     }
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+    pub fn set_quirks(&mut self, quirks: Quirks){
+        if quirks != self.quirks{
+            if let Some(jit) = self.jit.as_mut(){
+                jit.clear();
+            }
+        }
+        self.quirks = quirks;
+    }
+}
+
+impl Chip8 {
+    pub fn from_bytes(rom: &[u8]) -> Chip8 {
+        return Chip8::with_layout(rom, START_OF_PROG, START_OF_PROG);
+    }
+    pub(crate) fn with_layout(rom: &[u8], load_address: usize, entry_point: usize) -> Chip8 {
+        let seed = rand::random::<u64>();
+        let mut mem:[u8;MEM_SIZE] = [0; MEM_SIZE];
+        for (i, val) in rom.iter().take(MEM_SIZE - load_address).enumerate(){
+            mem[i + load_address] = *val;
+        }// load program
+        for i in 0..FONTS_SIZE{
+            mem[i + FONT_OFFSET] = get_font_val(i);
+        }// load fonts
+        let mut chip8 = Chip8 {
+            pc: entry_point,
+            index: 0,
+            load_address,
+            entry_point,
+            delay_timer: 0,
+            sound_timer: 0,
+            stack: Vec::new(),
+            stack_limit: VIP_STACK_DEPTH,
+            stack_overflow: false,
+            display: [PixelState::OFF; PIXELS],
+            memory: mem,
+            gp_reg: [0; 16],
+            keypad: [KeyState::OFF; 16],
+            input_queue: VecDeque::new(),
+            key_wait: None,
+            quirks: Quirks::default(),
+            rom: rom.to_vec(),
+            rom_hash: sha1_smol::Sha1::from(rom).digest().to_string(),
+            seed,
+            rng: seed,
+            cycles: 0,
+            frame: 0,
+            recording: None,
+            playback: None,
+            mem_hash: 0,
+            display_hash: 0,
+            decode_cache: DecodeCache::new(),
+            jit: None,
+            engine: Engine::Interpreter,
+            threaded: ThreadedCode::new(),
+            symbols: SymbolTable::new(),
+            breakpoints: BTreeSet::new(),
+            break_hit: None,
+            access_counts: None,
+            profiler: None,
+            cheats: Vec::new(),
+            patches: Vec::new(),
+            platform: Platform::Chip8,
+            variant: VariantState::default(),
+            mega: None
+        };
+        chip8.rehash();
+        return chip8;
+    }
+
+    // compiled blocks would run past breakpoints, counters and cheats, and
+    // only know the classic instruction set
+    fn single_stepping(&self) -> bool {
+        return !self.breakpoints.is_empty() || self.access_counts.is_some() || self.profiler.is_some() || self.cheats.iter().any(|c| c.is_enabled())
+            || self.platform.has_own_opcodes();
+    }
+
+    pub(crate) fn next_random(&mut self) -> u8 {
+        // splitmix64, so a seed fully determines CXNN results
+        self.rng = self.rng.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        return (z ^ (z >> 31)) as u8;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+use crate::cache::Handler;
+use crate::hash::Fnv;
+use crate::fonts::{FONTS_SIZE, FONT_OFFSET};
+use crate::instructions::Instruction;
+
+use wasm_bindgen::prelude::*;
+
+
+pub const MEGA_WIDTH: usize = 256;
+pub const MEGA_HEIGHT: usize = 192;
+const OPAQUE_WHITE: [u8; 4] = [0xFF; 4];
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    Normal = 0,
+    // sprite drawn at 25%, 50% or 75% opacity
+    Alpha25 = 1,
+    Alpha50 = 2,
+    Alpha75 = 3,
+    Add = 4,
+    Multiply = 5
+}
+
+impl BlendMode {
+    fn from_nibble(n: u8) -> BlendMode {
+        return match n {
+            1 => BlendMode::Alpha25,
+            2 => BlendMode::Alpha50,
+            3 => BlendMode::Alpha75,
+            4 => BlendMode::Add,
+            5 => BlendMode::Multiply,
+            _ => BlendMode::Normal
+        };
+    }
+    // both colours and the result as RGBA bytes
+    fn blend(&self, src: [u8; 4], dst: [u8; 4]) -> [u8; 4] {
+        let mix = |percent: u16| {
+            let mut out = [0xFF; 4];
+            for c in 0..3{
+                out[c] = ((src[c] as u16 * percent + dst[c] as u16 * (100 - percent)) / 100) as u8;
+            }
+            out
+        };
+        return match self {
+            BlendMode::Normal => src,
+            BlendMode::Alpha25 => mix(25),
+            BlendMode::Alpha50 => mix(50),
+            BlendMode::Alpha75 => mix(75),
+            BlendMode::Add => [src[0].saturating_add(dst[0]), src[1].saturating_add(dst[1]), src[2].saturating_add(dst[2]), 0xFF],
+            BlendMode::Multiply => {
+                let mul = |c: usize| (src[c] as u16 * dst[c] as u16 / 255) as u8;
+                [mul(0), mul(1), mul(2), 0xFF]
+            }
+        };
+    }
+}
+
+// A sample started by 060N
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Sample {
+    rate: u16,
+    data: Vec<u8>,
+    looping: bool
+}
+
+// State only MEGA-CHIP uses
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct MegaChip {
+    // 0011 turns the 256x192 mode on, 0010 goes back to the classic display
+    enabled: bool,
+    // RGBA bytes per pixel, ready for canvas image data
+    framebuffer: Vec<u32>,
+    // palette index last drawn to each pixel, for collisions
+    indices: Vec<u8>,
+    // RGBA, index 0 is always transparent
+    palette: Vec<[u8; 4]>,
+    sprite_width: usize,
+    sprite_height: usize,
+    screen_alpha: u8,
+    blend: BlendMode,
+    collision_color: u8,
+    // Code stays in the 4K the 12 bit opcodes reach, but 01NN NNNN points I
+    // anywhere in the ROM, so the whole image is kept for reading data. It
+    // comes from the ROM, so save states leave it out.
+    #[serde(skip)]
+    image: Vec<u8>,
+    #[serde(skip)]
+    load_address: usize,
+    sample: Option<Sample>
+}
+
+impl MegaChip {
+    pub(crate) fn new(image: &[u8], load_address: usize) -> MegaChip {
+        return MegaChip {
+            enabled: false,
+            framebuffer: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            indices: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            palette: vec![OPAQUE_WHITE; 256],
+            sprite_width: 8,
+            sprite_height: 1,
+            screen_alpha: 0xFF,
+            blend: BlendMode::Normal,
+            collision_color: 0,
+            image: image.to_vec(),
+            load_address,
+            sample: None
+        };
+    }
+    fn clear(&mut self){
+        self.framebuffer.iter_mut().for_each(|p| *p = 0);
+        self.indices.iter_mut().for_each(|p| *p = 0);
+    }
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let pixels = MEGA_WIDTH * MEGA_HEIGHT;
+        if self.framebuffer.len() != pixels || self.indices.len() != pixels || self.palette.len() != 256{
+            return Err("Save state has the wrong MEGA-CHIP display or palette size".to_string());
+        }
+        if !(1..=256).contains(&self.sprite_width) || !(1..=256).contains(&self.sprite_height){
+            return Err("Save state has an impossible MEGA-CHIP sprite size".to_string());
+        }
+        return Ok(());
+    }
+    // takes everything but the ROM image, which a state does not carry
+    pub(crate) fn restore(&mut self, saved: &MegaChip){
+        let image = std::mem::take(&mut self.image);
+        *self = MegaChip { image, load_address: self.load_address, ..saved.clone() };
+    }
+    pub(crate) fn hash_into(&self, fnv: &mut Fnv){
+        fnv.write(&[self.enabled as u8, self.screen_alpha, self.blend as u8, self.collision_color]);
+        fnv.write(&(self.sprite_width as u64).to_le_bytes());
+        fnv.write(&(self.sprite_height as u64).to_le_bytes());
+        for pixel in self.framebuffer.iter(){
+            fnv.write(&pixel.to_le_bytes());
+        }
+        fnv.write(&self.indices);
+        for color in self.palette.iter(){
+            fnv.write(color);
+        }
+        match &self.sample {
+            Some(sample) => {
+                fnv.write(&[1, sample.looping as u8]);
+                fnv.write(&sample.rate.to_le_bytes());
+                fnv.write(&(sample.data.len() as u64).to_le_bytes());
+                fnv.write(&sample.data);
+            }
+            None => fnv.write(&[0])
+        }
+    }
+}
+
+// MEGA-CHIP opcodes, on top of the classic set
+pub(crate) fn resolve_megachip(instr: &Instruction) -> Option<Handler> {
+    let handler: Handler = match (instr.operation, instr.x) {
+        (0x0, 0x0) if instr.nnn == 0x0E0 => Chip8::mega_clear,
+        (0x0, 0x0) if matches!(instr.nnn, 0x010 | 0x011) => Chip8::mega_mode,
+        (0x0, 0x1) => Chip8::long_index,
+        (0x0, 0x2..=0x5) | (0x0, 0x8..=0x9) => Chip8::mega_settings,
+        (0x0, 0x6) | (0x0, 0x7) if instr.y == 0 => Chip8::mega_sound,
+        (0xD, _) => Chip8::mega_draw,
+        _ => return None
+    };
+    return Some(handler);
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_megachip_mode(&self) -> bool {
+        return self.mega.as_ref().map(|m| m.enabled).unwrap_or(false);
+    }
+    // 256x192 RGBA pixels; only drawn to while MEGA-CHIP mode is on
+    pub fn get_framebuffer(&self) -> *const u32 {
+        return self.mega.as_ref().map(|m| m.framebuffer.as_ptr()).unwrap_or(std::ptr::null());
+    }
+    pub fn framebuffer_to_vec(&self) -> Vec<u32> {
+        return self.mega.as_ref().map(|m| m.framebuffer.clone()).unwrap_or_default();
+    }
+    // set by 05NN, for the frontend to fade the whole screen
+    pub fn get_screen_alpha(&self) -> u8 {
+        return self.mega.as_ref().map(|m| m.screen_alpha).unwrap_or(0xFF);
+    }
+    pub fn get_blend_mode(&self) -> BlendMode {
+        return self.mega.as_ref().map(|m| m.blend).unwrap_or(BlendMode::Normal);
+    }
+    // unsigned 8 bit samples of the sound 060N started, empty once stopped
+    pub fn get_sample(&self) -> Vec<u8> {
+        return self.mega.as_ref().and_then(|m| m.sample.as_ref()).map(|s| s.data.clone()).unwrap_or_default();
+    }
+    pub fn get_sample_rate(&self) -> u16 {
+        return self.mega.as_ref().and_then(|m| m.sample.as_ref()).map(|s| s.rate).unwrap_or(0);
+    }
+    pub fn get_sample_looping(&self) -> bool {
+        return self.mega.as_ref().and_then(|m| m.sample.as_ref()).map(|s| s.looping).unwrap_or(false);
+    }
+}
+
+impl Chip8 {
+    fn mega_state(&mut self) -> &mut MegaChip {
+        return self.mega.as_mut().expect("MEGA-CHIP state exists on its platform");
+    }
+    // reads past the 4K address space come from the ROM image
+    fn read_long(&mut self, addr: usize) -> u8 {
+        if addr < MEM_SIZE{
+            self.count_read(addr);
+            return self.memory[addr];
+        }
+        let mega = self.mega_state();
+        return addr.checked_sub(mega.load_address).and_then(|offset| mega.image.get(offset)).copied().unwrap_or(0);
+    }
+
+    fn mega_mode(&mut self, instr: Instruction){
+        // 0010 and 0011
+        self.mega_state().enabled = instr.nnn == 0x011;
+        self.mega_state().clear();
+    }
+    fn mega_clear(&mut self, instr: Instruction){
+        // 00E0
+        if !self.get_megachip_mode(){
+            self.clear(instr);
+            return;
+        }
+        self.mega_state().clear();
+    }
+    fn long_index(&mut self, instr: Instruction){
+        // 01NN NNNN, a four byte instruction
+        let low = u16::from_be_bytes([self.memory[self.pc % MEM_SIZE], self.memory[(self.pc + 1) % MEM_SIZE]]);
+        self.index = (instr.nn as usize) << 16 | low as usize;
+        self.pc += 2;
+    }
+    fn mega_settings(&mut self, instr: Instruction){
+        let nn = instr.nn as usize;
+        match instr.x {
+            // 02NN loads NN ARGB colours from I into palette entries 1 to NN
+            0x2 => {
+                for i in 0..nn{
+                    let argb: Vec<u8> = (0..4).map(|b| self.read_long(self.index + i * 4 + b)).collect();
+                    self.mega_state().palette[i + 1] = [argb[1], argb[2], argb[3], argb[0]];
+                }
+            }
+            // 03NN and 04NN, 0 meaning 256
+            0x3 => self.mega_state().sprite_width = if nn == 0 {256} else {nn},
+            0x4 => self.mega_state().sprite_height = if nn == 0 {256} else {nn},
+            // 05NN
+            0x5 => self.mega_state().screen_alpha = instr.nn,
+            // 080N
+            0x8 => self.mega_state().blend = BlendMode::from_nibble(instr.n),
+            // 09NN
+            _ => self.mega_state().collision_color = instr.nn
+        }
+    }
+    fn mega_sound(&mut self, instr: Instruction){
+        if instr.x == 0x7{
+            // 0700 stops the sound
+            self.mega_state().sample = None;
+            return;
+        }
+        // 060N plays the sound at I, looping when N is 0. It starts with a
+        // two byte sample rate, a three byte length and a reserved byte.
+        let header: Vec<u8> = (0..6).map(|i| self.read_long(self.index + i)).collect();
+        let rate = u16::from_be_bytes([header[0], header[1]]);
+        let len = u32::from_be_bytes([0, header[2], header[3], header[4]]) as usize;
+        // the length is clamped to the bytes there are: the 4K memory, then
+        // the rest of the ROM image
+        let start = self.index + 6;
+        let end = start.saturating_add(len);
+        let mut data = Vec::new();
+        if start < MEM_SIZE{
+            let low_end = end.min(MEM_SIZE);
+            for addr in start..low_end{
+                self.count_read(addr);
+            }
+            data.extend_from_slice(&self.memory[start..low_end]);
+        }
+        let mega = self.mega_state();
+        let from = (start.max(MEM_SIZE) - mega.load_address).min(mega.image.len());
+        let to = end.max(MEM_SIZE).saturating_sub(mega.load_address).clamp(from, mega.image.len());
+        data.extend_from_slice(&mega.image[from..to]);
+        let looping = instr.n == 0;
+        self.mega_state().sample = Some(Sample { rate, data, looping });
+    }
+    fn mega_draw(&mut self, instr: Instruction){
+        // DXYN, a byte per pixel naming a palette entry, 0 being transparent.
+        // Font sprites keep their one bit rows and are drawn in white.
+        if !self.get_megachip_mode(){
+            self.draw(instr);
+            return;
+        }
+        let (x, y) = (self.gp_reg[instr.x as usize] as usize, self.gp_reg[instr.y as usize] as usize);
+        let font = (FONT_OFFSET..FONT_OFFSET + FONTS_SIZE).contains(&self.index);
+        let (width, height) = if font {
+            (8, instr.n as usize)
+        } else {
+            let mega = self.mega_state();
+            (mega.sprite_width, mega.sprite_height)
+        };
+        let mut collided = false;
+        for row in 0..height{
+            let font_row = if font {self.read_long(self.index + row)} else {0};
+            for col in 0..width{
+                let color = if font {
+                    if (font_row >> (7 - col)) & 1 == 1 {0xFF} else {0}
+                } else {
+                    self.read_long(self.index + row * width + col)
+                };
+                let (px, py) = (x + col, y + row);
+                if color == 0 || px >= MEGA_WIDTH || py >= MEGA_HEIGHT{
+                    continue;
+                }
+                let mega = self.mega_state();
+                let idx = py * MEGA_WIDTH + px;
+                // drawing over the collision colour sets VF
+                collided |= mega.indices[idx] != 0 && mega.indices[idx] == mega.collision_color;
+                let src = if font {OPAQUE_WHITE} else {mega.palette[color as usize]};
+                let dst = mega.framebuffer[idx].to_le_bytes();
+                mega.indices[idx] = color;
+                mega.framebuffer[idx] = u32::from_le_bytes(mega.blend.blend(src, dst));
+            }
+        }
+        self.gp_reg[0xF] = collided as u8;
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+use crate::fonts::{FONTS_SIZE, FONT_OFFSET};
+
+use wasm_bindgen::prelude::*;
+
+
+// Where the VIP interpreter keeps its return addresses. This emulator keeps
+// the call stack outside the address space, but programs written for the VIP
+// stay clear of these bytes.
+const VIP_STACK_START: usize = 0xEA0;
+const VIP_STACK_END: usize = 0xED0;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionKind {
+    Interpreter = 0,
+    Font = 1,
+    Program = 2,
+    Stack = 3
+}
+
+// A labelled range of memory, `end` exclusive
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    kind: RegionKind,
+    start: usize,
+    end: usize
+}
+
+#[wasm_bindgen]
+impl MemoryRegion {
+    pub fn get_kind(&self) -> RegionKind {
+        return self.kind;
+    }
+    pub fn get_start(&self) -> usize {
+        return self.start;
+    }
+    pub fn get_end(&self) -> usize {
+        return self.end;
+    }
+    pub fn contains(&self, addr: usize) -> bool {
+        return self.start <= addr && addr < self.end;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // for zero copy views; write through `write_mem` so hashes and compiled
+    // code stay in step
+    pub fn get_memory(&self) -> *const u8 {
+        return self.memory.as_ptr();
+    }
+    // addresses wrap around the end of memory
+    pub fn read_mem(&self, start: usize, len: usize) -> Vec<u8> {
+        return (0..len).map(|i| self.memory[(start + i) % MEM_SIZE]).collect();
+    }
+    pub fn write_mem(&mut self, start: usize, bytes: &[u8]){
+        for (i, val) in bytes.iter().enumerate(){
+            self.write_memory(start + i, *val);
+        }
+    }
+    // The font sits inside the interpreter area and a long program can run
+    // into the stack, so regions may overlap. Sorted by start address.
+    pub fn get_memory_map(&self) -> Vec<MemoryRegion> {
+        let program_end = (self.load_address + self.rom.len()).min(MEM_SIZE);
+        return vec![
+            MemoryRegion { kind: RegionKind::Interpreter, start: 0, end: self.load_address },
+            MemoryRegion { kind: RegionKind::Font, start: FONT_OFFSET, end: FONT_OFFSET + FONTS_SIZE },
+            MemoryRegion { kind: RegionKind::Program, start: self.load_address, end: program_end },
+            MemoryRegion { kind: RegionKind::Stack, start: VIP_STACK_START, end: VIP_STACK_END }
+        ];
+    }
+    pub fn memory_map_to_json(&self) -> String {
+        return serde_json::to_string(&self.get_memory_map()).expect("Memory map is always serializable");
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, KeyState};
+use crate::quirks::Quirks;
+use crate::analyzer::Platform;
+use crate::patch::RomPatch;
+
+use wasm_bindgen::prelude::*;
+
+
+const MOVIE_VERSION: u32 = 1;
+
+// movies from before the layout was recorded ran at the classic 0x200
+fn classic_start() -> usize {
+    return 0x200;
+}
+
+// A keypad change. `cycle` is the instruction count when the change reached
+// the keypad and is what playback keys on; `frame` is kept for display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovieEvent {
+    pub frame: u64,
+    pub cycle: u64,
+    pub key: u8,
+    pub state: KeyState
+}
+
+// Everything needed to replay a session from power on
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movie {
+    version: u32,
+    rom_sha1: String,
+    seed: u64,
+    quirks: Quirks,
+    // the machine the ROM ran on; a replay on any other diverges
+    #[serde(default)]
+    platform: Platform,
+    #[serde(default = "classic_start")]
+    load_address: usize,
+    #[serde(default = "classic_start")]
+    entry_point: usize,
+    #[serde(default)]
+    patches: Vec<RomPatch>,
+    frames: u64,
+    events: Vec<MovieEvent>
+}
+
+pub(crate) struct Playback {
+    movie: Movie,
+    cursor: usize
+}
+
+#[wasm_bindgen]
+impl Movie {
+    pub fn get_rom_hash(&self) -> String {
+        return self.rom_sha1.clone();
+    }
+    pub fn get_seed(&self) -> u64 {
+        return self.seed;
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+    pub fn get_platform(&self) -> Platform {
+        return self.platform;
+    }
+    pub fn get_load_address(&self) -> usize {
+        return self.load_address;
+    }
+    pub fn get_entry_point(&self) -> usize {
+        return self.entry_point;
+    }
+    pub fn get_patch_count(&self) -> usize {
+        return self.patches.len();
+    }
+    pub fn get_frames(&self) -> u64 {
+        return self.frames;
+    }
+    pub fn event_count(&self) -> usize {
+        return self.events.len();
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Movie is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<Movie, String> {
+        let movie: Movie = serde_json::from_str(json).map_err(|e| format!("Invalid movie: {}", e))?;
+        if movie.version != MOVIE_VERSION{
+            return Err(format!("Unsupported movie version {}", movie.version));
+        }
+        return Ok(movie);
+    }
+}
+
+impl Movie {
+    pub fn events(&self) -> &[MovieEvent] {
+        return &self.events;
+    }
+    pub(crate) fn record(&mut self, frame: u64, cycle: u64, key: u8, state: KeyState){
+        self.events.push(MovieEvent { frame, cycle, key, state });
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn start_recording(&mut self){
+        // movies always start from power on
+        self.reset();
+        self.recording = Some(Movie {
+            version: MOVIE_VERSION,
+            rom_sha1: self.rom_hash.clone(),
+            seed: self.seed,
+            quirks: self.quirks,
+            platform: self.platform,
+            load_address: self.load_address,
+            entry_point: self.entry_point,
+            patches: self.patches.clone(),
+            frames: 0,
+            events: Vec::new()
+        });
+    }
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        let mut movie = self.recording.take()?;
+        movie.frames = self.frame;
+        return Some(movie);
+    }
+    pub fn is_recording(&self) -> bool {
+        return self.recording.is_some();
+    }
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), String> {
+        if movie.rom_sha1 != self.rom_hash{
+            return Err(format!("Movie was recorded on ROM {} but {} is loaded", movie.rom_sha1, self.rom_hash));
+        }
+        if movie.platform != self.platform{
+            return Err(format!("Movie was recorded on {:?} but {:?} is running", movie.platform, self.platform));
+        }
+        if (movie.load_address, movie.entry_point) != (self.load_address, self.entry_point){
+            return Err(format!(
+                "Movie was recorded with the ROM at {:#X} starting at {:#X}, but it is at {:#X} starting at {:#X}",
+                movie.load_address, movie.entry_point, self.load_address, self.entry_point
+            ));
+        }
+        if movie.patches != self.patches{
+            return Err("Movie was recorded with different patches applied".to_string());
+        }
+        self.recording = None;
+        self.quirks = movie.quirks;
+        self.set_seed(movie.seed);
+        self.reset();
+        self.playback = Some(Playback { movie, cursor: 0 });
+        return Ok(());
+    }
+    pub fn is_playing(&self) -> bool {
+        return self.playback.is_some();
+    }
+    pub fn stop_playback(&mut self){
+        self.playback = None;
+    }
+}
+
+impl Chip8 {
+    pub(crate) fn poll_playback(&mut self){
+        let Some(playback) = self.playback.as_mut() else { return };
+        let events = &playback.movie.events;
+        while playback.cursor < events.len() && events[playback.cursor].cycle <= self.cycles{
+            let event = events[playback.cursor];
+            self.keypad[event.key as usize] = event.state;
+            playback.cursor += 1;
+        }
+    }
+    pub(crate) fn end_finished_playback(&mut self){
+        let Some(playback) = self.playback.as_ref() else { return };
+        if playback.cursor == playback.movie.events.len() && self.frame >= playback.movie.frames{
+            self.playback = None;
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryInto;
+use std::rc::Rc;
+use crate::{Chip8, KeyState};
+use crate::state::SaveState;
+
+use wasm_bindgen::prelude::*;
+
+
+const INPUT_PACKET: u8 = 0;
+const HASH_PACKET: u8 = 1;
+// columns 1-2 and 3-C of the COSMAC keypad, which is how two player ROMs
+// like pong split the keys
+const LEFT_KEYS: u16 = 1 << 0x1 | 1 << 0x2 | 1 << 0x4 | 1 << 0x5 | 1 << 0x7 | 1 << 0x8 | 1 << 0xA | 1 << 0x0;
+const RIGHT_KEYS: u16 = !LEFT_KEYS;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeypadHalf {
+    Left = 0,
+    Right = 1
+}
+
+impl KeypadHalf {
+    pub fn mask(&self) -> u16 {
+        match self {
+            KeypadHalf::Left => LEFT_KEYS,
+            KeypadHalf::Right => RIGHT_KEYS
+        }
+    }
+}
+
+// Moves opaque packets between the two peers. Delivery must be reliable and
+// ordered, which both WebSocket and ordered WebRTC data channels provide.
+pub trait Transport {
+    fn send(&mut self, packet: Vec<u8>);
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+// In-process transport, mainly for tests
+pub struct LoopbackTransport {
+    outgoing: Rc<RefCell<VecDeque<Vec<u8>>>>,
+    incoming: Rc<RefCell<VecDeque<Vec<u8>>>>
+}
+
+impl LoopbackTransport {
+    pub fn pair() -> (LoopbackTransport, LoopbackTransport) {
+        let a = Rc::new(RefCell::new(VecDeque::new()));
+        let b = Rc::new(RefCell::new(VecDeque::new()));
+        return (
+            LoopbackTransport { outgoing: a.clone(), incoming: b.clone() },
+            LoopbackTransport { outgoing: b, incoming: a }
+        );
+    }
+}
+
+impl Transport for LoopbackTransport {
+    fn send(&mut self, packet: Vec<u8>){
+        self.outgoing.borrow_mut().push_back(packet);
+    }
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        return self.incoming.borrow_mut().pop_front();
+    }
+}
+
+// Buffers packets for a frontend that owns the actual socket
+#[derive(Default)]
+pub struct ChannelTransport {
+    inbox: VecDeque<Vec<u8>>,
+    outbox: VecDeque<Vec<u8>>
+}
+
+impl ChannelTransport {
+    pub fn deliver(&mut self, packet: Vec<u8>){
+        self.inbox.push_back(packet);
+    }
+    pub fn take_outgoing(&mut self) -> Option<Vec<u8>> {
+        return self.outbox.pop_front();
+    }
+}
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, packet: Vec<u8>){
+        self.outbox.push_back(packet);
+    }
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        return self.inbox.pop_front();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Packet {
+    Input { frame: u64, keys: u16 },
+    Hash { frame: u64, hash: u64 }
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Packet::Input { frame, keys } => {
+                bytes.push(INPUT_PACKET);
+                bytes.extend_from_slice(&frame.to_le_bytes());
+                bytes.extend_from_slice(&keys.to_le_bytes());
+            }
+            Packet::Hash { frame, hash } => {
+                bytes.push(HASH_PACKET);
+                bytes.extend_from_slice(&frame.to_le_bytes());
+                bytes.extend_from_slice(&hash.to_le_bytes());
+            }
+        }
+        return bytes;
+    }
+    fn decode(bytes: &[u8]) -> Option<Packet> {
+        let frame = u64::from_le_bytes(bytes.get(1..9)?.try_into().ok()?);
+        match bytes[0] {
+            INPUT_PACKET if bytes.len() == 11 => {
+                let keys = u16::from_le_bytes(bytes[9..11].try_into().ok()?);
+                Some(Packet::Input { frame, keys })
+            }
+            HASH_PACKET if bytes.len() == 17 => {
+                let hash = u64::from_le_bytes(bytes[9..17].try_into().ok()?);
+                Some(Packet::Hash { frame, hash })
+            }
+            _ => None
+        }
+    }
+}
+
+// Lockstep session with rollback. Each peer owns one half of the keypad and
+// sends its input for every frame; when the remote input for a frame is not
+// in yet we predict it (last known input) and rewind once it arrives and
+// turns out different. Every `hash_interval` confirmed frames both sides
+// exchange a hash of the state so divergence is caught early.
+pub struct NetplaySession<T: Transport> {
+    chip8: Chip8,
+    transport: T,
+    local_mask: u16,
+    cycles_per_frame: u32,
+    input_delay: u64,
+    max_rollback: u64,
+    hash_interval: u64,
+    frame: u64,
+    confirmed: u64,
+    local: BTreeMap<u64, u16>,
+    remote: BTreeMap<u64, u16>,
+    used_remote: BTreeMap<u64, u16>,
+    snapshots: BTreeMap<u64, SaveState>,
+    local_hashes: BTreeMap<u64, u64>,
+    remote_hashes: BTreeMap<u64, u64>,
+    rollbacks: u64,
+    desync: Option<u64>
+}
+
+impl<T: Transport> NetplaySession<T> {
+    // both peers must create the session with the same ROM, seed and quirks
+    pub fn new(chip8: Chip8, transport: T, half: KeypadHalf) -> NetplaySession<T> {
+        return NetplaySession {
+            chip8,
+            transport,
+            local_mask: half.mask(),
+            cycles_per_frame: 10,
+            input_delay: 2,
+            max_rollback: 8,
+            hash_interval: 30,
+            frame: 0,
+            confirmed: 0,
+            local: BTreeMap::new(),
+            remote: BTreeMap::new(),
+            used_remote: BTreeMap::new(),
+            snapshots: BTreeMap::new(),
+            local_hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new(),
+            rollbacks: 0,
+            desync: None
+        }
+    }
+    pub fn set_cycles_per_frame(&mut self, cycles: u32){
+        self.cycles_per_frame = cycles;
+    }
+    pub fn set_input_delay(&mut self, frames: u64){
+        self.input_delay = frames;
+    }
+    pub fn set_max_rollback(&mut self, frames: u64){
+        self.max_rollback = frames.max(1);
+    }
+    pub fn set_hash_interval(&mut self, frames: u64){
+        self.hash_interval = frames.max(1);
+    }
+    pub fn chip8(&self) -> &Chip8 {
+        return &self.chip8;
+    }
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        return &mut self.chip8;
+    }
+    pub fn transport_mut(&mut self) -> &mut T {
+        return &mut self.transport;
+    }
+    pub fn frame(&self) -> u64 {
+        return self.frame;
+    }
+    pub fn confirmed_frame(&self) -> u64 {
+        return self.confirmed;
+    }
+    pub fn rollbacks(&self) -> u64 {
+        return self.rollbacks;
+    }
+    // first frame whose state hash differed from the peer's
+    pub fn desync_frame(&self) -> Option<u64> {
+        return self.desync;
+    }
+
+    // Runs one frame with `keys` (bit per key) as the local input. Returns
+    // false without advancing when too far ahead of the remote peer.
+    pub fn advance(&mut self, keys: u16) -> bool {
+        self.receive();
+        self.rollback_mispredictions();
+        if self.frame - self.confirmed >= self.max_rollback{
+            return false;
+        }
+        let input_frame = self.frame + self.input_delay;
+        let keys = keys & self.local_mask;
+        self.local.insert(input_frame, keys);
+        self.transport.send(Packet::Input { frame: input_frame, keys }.encode());
+        self.simulate_frame();
+        self.confirm_frames();
+        self.check_hashes();
+        return true;
+    }
+
+    fn receive(&mut self){
+        while let Some(bytes) = self.transport.recv(){
+            match Packet::decode(&bytes) {
+                Some(Packet::Input { frame, keys }) => {
+                    self.remote.insert(frame, keys & !self.local_mask);
+                }
+                Some(Packet::Hash { frame, hash }) => {
+                    self.remote_hashes.insert(frame, hash);
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn local_input(&self, frame: u64) -> u16 {
+        // nobody can send input for the frames hidden by the input delay
+        return self.local.get(&frame).copied().unwrap_or(0);
+    }
+    fn remote_input(&self, frame: u64) -> Option<u16> {
+        if frame < self.input_delay{
+            return Some(0);
+        }
+        return self.remote.get(&frame).copied();
+    }
+    fn predict_remote(&self, frame: u64) -> u16 {
+        if let Some(keys) = self.remote_input(frame){
+            return keys;
+        }
+        return self.remote.range(..frame).next_back().map(|(_, keys)| *keys).unwrap_or(0);
+    }
+
+    fn rollback_mispredictions(&mut self){
+        let mispredicted = (self.confirmed..self.frame).find(|frame| {
+            match self.remote_input(*frame) {
+                Some(keys) => self.used_remote.get(frame) != Some(&keys),
+                None => false
+            }
+        });
+        let Some(start) = mispredicted else { return };
+        let target = self.frame;
+        let snapshot = self.snapshots.get(&start).expect("Snapshot kept for unconfirmed frame").clone();
+        self.chip8.load_state(&snapshot).expect("Snapshot belongs to this ROM");
+        self.frame = start;
+        while self.frame < target{
+            self.simulate_frame();
+        }
+        self.rollbacks += 1;
+    }
+
+    fn simulate_frame(&mut self){
+        let frame = self.frame;
+        self.snapshots.insert(frame, self.chip8.save_state());
+        let remote = self.predict_remote(frame);
+        self.used_remote.insert(frame, remote);
+        let keys = self.local_input(frame) | remote;
+        for key in 0..16{
+            let state = if keys & (1 << key) != 0 {KeyState::ON} else {KeyState::OFF};
+            self.chip8.apply_key(key, state);
+        }
+        self.chip8.run_frame(self.cycles_per_frame);
+        self.frame += 1;
+    }
+
+    fn confirm_frames(&mut self){
+        while self.confirmed < self.frame && self.remote_input(self.confirmed).is_some(){
+            let frame = self.confirmed;
+            self.confirmed += 1;
+            if frame.is_multiple_of(self.hash_interval){
+                let hash = match self.snapshots.get(&self.confirmed) {
+                    Some(state) => state.state_hash(),
+                    None => self.chip8.incremental_state_hash()
+                };
+                self.local_hashes.insert(frame, hash);
+                self.transport.send(Packet::Hash { frame, hash }.encode());
+            }
+        }
+        // everything before the confirmed frame is final
+        let confirmed = self.confirmed;
+        self.snapshots.retain(|frame, _| *frame >= confirmed);
+        self.used_remote.retain(|frame, _| *frame >= confirmed);
+        self.local.retain(|frame, _| *frame >= confirmed);
+        self.remote.retain(|frame, _| *frame + 1 >= confirmed);
+    }
+
+    fn check_hashes(&mut self){
+        let frames: Vec<u64> = self.remote_hashes.keys()
+            .filter(|frame| self.local_hashes.contains_key(frame))
+            .copied()
+            .collect();
+        for frame in frames{
+            let remote = self.remote_hashes.remove(&frame);
+            let local = self.local_hashes.remove(&frame);
+            if remote != local && self.desync.is_none(){
+                self.desync = Some(frame);
+            }
+        }
+    }
+}
+
+// Session driven from JS, which forwards packets over its data channel
+#[wasm_bindgen]
+pub struct Netplay {
+    session: NetplaySession<ChannelTransport>
+}
+
+#[wasm_bindgen]
+impl Netplay {
+    pub fn new(rom: &js_sys::Uint8Array, seed: u64, half: KeypadHalf) -> Netplay {
+        let mut chip8 = Chip8::new(rom);
+        chip8.set_seed(seed);
+        return Netplay { session: NetplaySession::new(chip8, ChannelTransport::default(), half) };
+    }
+    pub fn advance(&mut self, keys: u16) -> bool {
+        return self.session.advance(keys);
+    }
+    pub fn receive_packet(&mut self, packet: &[u8]){
+        self.session.transport_mut().deliver(packet.to_vec());
+    }
+    pub fn poll_outgoing(&mut self) -> Option<Vec<u8>> {
+        return self.session.transport_mut().take_outgoing();
+    }
+    pub fn set_cycles_per_frame(&mut self, cycles: u32){
+        self.session.set_cycles_per_frame(cycles);
+    }
+    pub fn set_input_delay(&mut self, frames: u64){
+        self.session.set_input_delay(frames);
+    }
+    pub fn get_display(&self) -> *const crate::PixelState {
+        return self.session.chip8().get_display();
+    }
+    pub fn get_frame(&self) -> u64 {
+        return self.session.frame();
+    }
+    pub fn desync_frame(&self) -> Option<u64> {
+        return self.session.desync_frame();
+    }
+}
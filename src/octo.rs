@@ -0,0 +1,164 @@
+use std::convert::TryInto;
+use serde::{Deserialize, Serialize};
+use crate::Chip8;
+use crate::keymap::Keymap;
+use crate::profile::RomProfile;
+use crate::quirks::Quirks;
+
+use wasm_bindgen::prelude::*;
+
+
+// Octo's defaults for anything an options object leaves out
+const DEFAULT_TICK_RATE: u32 = 20;
+const DEFAULT_BACKGROUND: &str = "#996600";
+const DEFAULT_FILL: &str = "#FFCC00";
+const DEFAULT_FILL2: &str = "#FF6600";
+const DEFAULT_BLEND: &str = "#662200";
+const DEFAULT_BUZZ: &str = "#FFAA00";
+const DEFAULT_QUIET: &str = "#000000";
+
+// The options object Octo writes into cartridges and options.json. Every
+// quirk flag is off unless set, which is Octo's XO-CHIP behaviour.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OctoOptions {
+    tickrate: Option<u32>,
+    background_color: Option<String>,
+    fill_color: Option<String>,
+    fill_color2: Option<String>,
+    blend_color: Option<String>,
+    buzz_color: Option<String>,
+    quiet_color: Option<String>,
+    // VX is shifted in place, ignoring VY
+    shift_quirks: bool,
+    // FX55/FX65 leave I alone
+    load_store_quirks: bool,
+    // sprites are clipped at the edges instead of wrapping
+    clip_quirks: bool,
+    // BNNN is BXNN
+    jump_quirks: bool,
+    // 8XY1/8XY2/8XY3 clear VF
+    logic_quirks: bool,
+    // VF is written before the result; we always write it last
+    vf_order_quirks: bool,
+    // DXYN waits for vblank; we do not emulate the wait
+    v_blank_quirks: bool,
+    screen_rotation: Option<u32>,
+    max_size: Option<u32>,
+    touch_input_mode: Option<String>,
+    font_style: Option<String>
+}
+
+#[wasm_bindgen]
+impl OctoOptions {
+    pub fn from_json(json: &str) -> Result<OctoOptions, String> {
+        return serde_json::from_str(json).map_err(|e| format!("Invalid Octo options: {}", e));
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Octo options are always serializable");
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        return Quirks {
+            shift_uses_vy: !self.shift_quirks,
+            memory_increments_index: !self.load_store_quirks,
+            wrap_sprites: !self.clip_quirks,
+            jump_uses_vx: self.jump_quirks,
+            logic_resets_vf: self.logic_quirks,
+            ..Quirks::default()
+        };
+    }
+    // instructions per frame
+    pub fn get_tick_rate(&self) -> u32 {
+        return self.tickrate.unwrap_or(DEFAULT_TICK_RATE);
+    }
+    // background, plane 1, plane 2, both planes; the order the ROM database uses
+    pub fn get_pixel_colors(&self) -> Vec<String> {
+        let pick = |color: &Option<String>, default: &str| color.clone().unwrap_or_else(|| default.to_string());
+        return vec![
+            pick(&self.background_color, DEFAULT_BACKGROUND),
+            pick(&self.fill_color, DEFAULT_FILL),
+            pick(&self.fill_color2, DEFAULT_FILL2),
+            pick(&self.blend_color, DEFAULT_BLEND)
+        ];
+    }
+    pub fn get_buzzer_color(&self) -> String {
+        return self.buzz_color.clone().unwrap_or_else(|| DEFAULT_BUZZ.to_string());
+    }
+    pub fn get_silence_color(&self) -> String {
+        return self.quiet_color.clone().unwrap_or_else(|| DEFAULT_QUIET.to_string());
+    }
+    // Octo has one fixed layout
+    pub fn get_keymap(&self) -> Keymap {
+        return Keymap::octo();
+    }
+    pub fn profile(&self) -> RomProfile {
+        let mut profile = RomProfile::new();
+        profile.set_quirks(self.get_quirks());
+        profile.set_keymap(self.get_keymap());
+        return profile;
+    }
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    program: String,
+    #[serde(default)]
+    options: OctoOptions
+}
+
+// An Octo program: its source and the options it was saved with
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoCartridge {
+    source: String,
+    options: OctoOptions
+}
+
+#[wasm_bindgen]
+impl OctoCartridge {
+    // `.8o` source, optionally with the options.json that came with it
+    pub fn from_source(source: &str, options: Option<String>) -> Result<OctoCartridge, String> {
+        let options = match options {
+            Some(json) => OctoOptions::from_json(&json)?,
+            None => OctoOptions::default()
+        };
+        return Ok(OctoCartridge { source: source.to_string(), options });
+    }
+    // A cartridge is a GIF whose palette indices carry the payload in their
+    // low two bits, four pixels to a byte. The payload is a 32 bit big endian
+    // length followed by that many bytes of JSON holding the program source
+    // and its options.
+    pub fn from_gif(gif: &[u8]) -> Result<OctoCartridge, String> {
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(gif).map_err(|e| format!("Invalid cartridge image: {}", e))?;
+        let frame = decoder.read_next_frame()
+            .map_err(|e| format!("Invalid cartridge image: {}", e))?
+            .ok_or("Cartridge image has no frames")?;
+        let bytes: Vec<u8> = frame.buffer.chunks_exact(4)
+            .map(|px| px.iter().fold(0, |byte, p| (byte << 2) | (p & 3)))
+            .collect();
+        if bytes.len() < 4{
+            return Err("Cartridge image is too small".to_string());
+        }
+        let len = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+        let json = bytes.get(4..4 + len).ok_or("Cartridge payload is truncated")?;
+        let payload: Payload = serde_json::from_slice(json).map_err(|e| format!("Invalid cartridge payload: {}", e))?;
+        return Ok(OctoCartridge { source: payload.program, options: payload.options });
+    }
+    pub fn get_source(&self) -> String {
+        return self.source.clone();
+    }
+    pub fn get_options(&self) -> OctoOptions {
+        return self.options.clone();
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // the tick rate, colours and keymap are applied by the frontend
+    pub fn apply_octo_options(&mut self, options: &OctoOptions){
+        self.set_quirks(options.get_quirks());
+    }
+}
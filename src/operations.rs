@@ -4,15 +4,11 @@ use crate::log;
 use crate::Chip8;
 use crate::FONT_OFFSET;
 use crate::Instruction;
+use crate::{MEM_SIZE, PIXELS};
 use wasm_bindgen::prelude::*;
 
 
 
-const MEM_SIZE: usize = 4096;
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
-const PIXELS: usize = DISPLAY_HEIGHT * DISPLAY_WIDTH;
-const START_OF_PROG: usize = 0x200;
 const ADD: u8 = 4;
 const SUB_XY: u8 = 5;
 const SUB_YX: u8 = 7;
@@ -32,7 +28,8 @@ impl Chip8 {
         // 8XYN
         let x = instr.x as usize;
         let y = instr.y as usize;
-        match instr.n {
+        let n = instr.n;
+        match n {
             0 => self.gp_reg[x] = self.gp_reg[y],
             1 => self.gp_reg[x] |= self.gp_reg[y],
             2 => self.gp_reg[x] &= self.gp_reg[y],
@@ -41,6 +38,9 @@ impl Chip8 {
             _ => console_log!("Error with {:?}",instr.to_string())
 
         }
+        if self.quirks.logic_resets_vf && matches!(n, 1..=3){
+            self.gp_reg[0xF] = 0;
+        }
     }
     pub fn zero(&mut self, instr: Instruction){
         match instr.nn {
@@ -63,12 +63,23 @@ impl Chip8 {
     }
     pub fn push(&mut self, instr:Instruction){
         // 2NNN
+        if self.stack.len() >= self.stack_limit{
+            // a runaway recursion overflows every frame, so only the first is logged
+            if !self.stack_overflow{
+                console_log!("Stack overflow calling {:03X} from {:03X}", instr.nnn, self.pc.wrapping_sub(2));
+            }
+            self.stack_overflow = true;
+            return;
+        }
         self.stack.push(self.pc);
         self.pc = instr.nnn as usize;
     }
-    pub fn pop(&mut self, instr: Instruction){
+    pub fn pop(&mut self, _instr: Instruction){
         // 00EE
-        self.pc = self.stack.pop().expect("Stack is Empty!!!");
+        match self.stack.pop() {
+            Some(addr) => self.pc = addr,
+            None => console_log!("Return with an empty stack at {:03X}", self.pc)
+        }
 
     }
     pub fn skip_if_eq(&mut self, instr: Instruction){
@@ -98,7 +109,8 @@ impl Chip8 {
 
     pub fn skip_key(&mut self, instr: Instruction){
         // EX9E and EXA1
-        let key = self.gp_reg[instr.x as usize] as usize;
+        // only the low nibble reaches the keypad
+        let key = (self.gp_reg[instr.x as usize] & 0xF) as usize;
         if instr.nn == 0x9E && self.keypad[key] == KeyState::ON {
             self.pc += 2;
         }
@@ -110,7 +122,7 @@ impl Chip8 {
         // 00E0
         if instr.nnn == 0x0E0{
             for i in 0..PIXELS{
-                self.display[i] = PixelState::OFF;
+                self.set_pixel(i, PixelState::OFF);
             }
         }
     }
@@ -120,8 +132,9 @@ impl Chip8 {
         self.pc = instr.nnn as usize;
     }
     pub fn offset_jump(&mut self, instr: Instruction){
-        // BNNN
-        self.pc = self.gp_reg[0] as usize + instr.nnn as usize;
+        // BNNN, or BXNN with the jump quirk
+        let reg = if self.quirks.jump_uses_vx {instr.x as usize} else {0};
+        self.pc = self.gp_reg[reg] as usize + instr.nnn as usize;
     }
     pub fn set_register(&mut self, instr: Instruction){
         // 6XNN
@@ -155,6 +168,17 @@ impl Chip8 {
 
     pub fn get_key(&mut self, instr: Instruction){
         // FX0A
+        if let Some(key) = self.key_wait{
+            // a key went down, wait for it to come back up
+            if self.keypad[key as usize] == KeyState::OFF{
+                self.gp_reg[instr.x as usize] = key;
+                self.key_wait = None;
+            }
+            else{
+                self.pc -= 2;
+            }
+            return;
+        }
         let mut pressed_key = u8::MAX;
         for i in 0..16{
             if self.keypad[i] == KeyState::ON{
@@ -165,9 +189,13 @@ impl Chip8 {
         if pressed_key == u8::MAX{
             self.pc -= 2;
         }
-        else{
+        else if self.quirks.key_wait_on_press{
             self.gp_reg[instr.x as usize] = pressed_key; 
         }
+        else{
+            self.key_wait = Some(pressed_key);
+            self.pc -= 2;
+        }
         
     }
     pub fn get_font(&mut self, instr: Instruction){
@@ -181,21 +209,32 @@ impl Chip8 {
         // FX55
         let temp_idx = self.index;
         for i in 0..(instr.x + 1){
-            self.memory[temp_idx + i as usize] = self.gp_reg[i as usize];
+            self.write_memory(temp_idx + i as usize, self.gp_reg[i as usize]);
+            self.count_write(temp_idx + i as usize);
         }
+        self.advance_index(instr.x);
     }
     pub fn load(&mut self, instr: Instruction){
         // FX65
         let temp_idx = self.index;
         for i in 0..(instr.x + 1){
-            self.gp_reg[i as usize]  = self.memory[temp_idx + i as usize]; 
+            self.gp_reg[i as usize]  = self.memory[(temp_idx + i as usize) % MEM_SIZE]; 
+            self.count_read(temp_idx + i as usize);
+        }
+        self.advance_index(instr.x);
+    }
+    fn advance_index(&mut self, x: u8){
+        if self.quirks.memory_increments_index{
+            // I is a 16 bit register on the VIP
+            self.index = (self.index + x as usize + 1) & 0xFFFF;
         }
     }
     pub fn decimal_conversion(&mut self, instr: Instruction){
         // FX33
         let mut vx = self.gp_reg[instr.x as usize];
         for i in (0..3).rev(){
-            self.memory[self.index + i] = vx % 10;
+            self.write_memory(self.index + i, vx % 10);
+            self.count_write(self.index + i);
             vx /= 10;
         }
     }
@@ -209,26 +248,28 @@ impl Chip8 {
         let mut flag: u8 = 0;
         if instr.n == ADD {
             val = vx.wrapping_add(vy);
-            flag = if vx.checked_add(vy) == None {1} else {0};
+            flag = if vx.checked_add(vy).is_none() {1} else {0};
         }
         if instr.n == SUB_XY{
             val = vx.wrapping_sub(vy);
-            flag = if vx.checked_sub(vy) == None {0} else {1};
+            flag = if vx.checked_sub(vy).is_none() {0} else {1};
 
         }
         if instr.n == SUB_YX {
             val = vy.wrapping_sub(vx);
-            flag = if vy.checked_sub(vx) == None {0} else {1};
+            flag = if vy.checked_sub(vx).is_none() {0} else {1};
         }
+        let shifted = if self.quirks.shift_uses_vy {vy} else {vx};
         if instr.n == SHIFT_LEFT {
-            val = vx.wrapping_mul(2);
-            flag = (vx & 0x80) >> 7;
+            val = shifted.wrapping_mul(2);
+            flag = (shifted & 0x80) >> 7;
 
         }
         if instr.n == SHIFT_RIGHT {
-            val = vx.saturating_div(2);
-            flag = vx & 1;
+            val = shifted.saturating_div(2);
+            flag = shifted & 1;
         }
+        // the flag goes last so it wins when X is F
         self.gp_reg[x] = val;
         self.gp_reg[0xF] = flag;
     }
@@ -236,35 +277,42 @@ impl Chip8 {
 
     pub fn random(&mut self, instr: Instruction){
         // CXNN
-        let random = rand::random::<u8>();
+        let random = self.next_random();
         self.gp_reg[instr.x as usize] = random & instr.nn;
     }
     pub fn draw(&mut self, instr: Instruction){
         // DXYN
+        let height = self.display_height() as u16;
         let x = (self.gp_reg[instr.x as usize] % 64) as u16;
-        let mut y = (self.gp_reg[instr.y as usize] % 32) as u16;
+        let y = self.gp_reg[instr.y as usize] as u16 % height;
         self.gp_reg[0xF] = 0;
         for i in 0..instr.n{
             let temp_idx = self.index + i as usize;
-            let sprite_byte = self.memory[temp_idx];
+            let sprite_byte = self.memory[temp_idx % MEM_SIZE];
+            self.count_read(temp_idx);
             for mask_idx in 0..8{
-                let pixel_idx = ((x + mask_idx) + (y * 64)) as usize;
-                if pixel_idx < 2048 {
+                let (mut px, mut py) = (x + mask_idx, y + i as u16);
+                if self.quirks.wrap_sprites{
+                    px %= 64;
+                    py %= height;
+                }
+                // clipped at the edges rather than bleeding into the next row
+                if px < 64 && py < height {
+                    let pixel_idx = (px + py * 64) as usize;
                     let pixel = self.display[pixel_idx];
-                    let bit = (sprite_byte >> 7 - mask_idx) & 1;
+                    let bit = (sprite_byte >> (7 - mask_idx)) & 1;
                     if bit == 1{
                         if pixel == PixelState::ON{
-                            self.display[pixel_idx] = PixelState::OFF;
+                            self.set_pixel(pixel_idx, PixelState::OFF);
                             self.gp_reg[0xF] = 1;
                         }
                         else{
-                            self.display[pixel_idx] = PixelState::ON;
+                            self.set_pixel(pixel_idx, PixelState::ON);
                         }
                     }
                 }
 
             }
-            y += 1;
         }
     }
 }
\ No newline at end of file
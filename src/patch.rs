@@ -0,0 +1,297 @@
+use std::convert::TryInto;
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE, START_OF_PROG};
+
+use wasm_bindgen::prelude::*;
+
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Edits {
+    // (memory address, value) as typed in by the user
+    Codes(Vec<(usize, u8)>),
+    Ips(Vec<u8>),
+    Bps(Vec<u8>)
+}
+
+// A change to a ROM image, kept apart from the ROM itself and applied each
+// time it is loaded
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomPatch {
+    // the ROM the patch was made for, when known
+    rom_sha1: Option<String>,
+    edits: Edits
+}
+
+#[wasm_bindgen]
+impl RomPatch {
+    // `2A4:07 2A5:1F`, a memory address and a byte per code, in hex,
+    // separated by whitespace or commas. Addresses must fall after the load
+    // address of the ROM they are applied to.
+    pub fn from_codes(codes: &str, rom_sha1: Option<String>) -> Result<RomPatch, String> {
+        let mut edits = Vec::new();
+        for code in codes.split(|c: char| c.is_whitespace() || c == ',').filter(|c| !c.is_empty()){
+            let (addr, value) = code.split_once(':').ok_or(format!("Patch code {} is not address:value", code))?;
+            let hex = |s: &str| s.trim_start_matches("0x").trim_start_matches("0X").to_string();
+            let addr = usize::from_str_radix(&hex(addr), 16).map_err(|_| format!("Bad address in patch code {}", code))?;
+            let value = u8::from_str_radix(&hex(value), 16).map_err(|_| format!("Bad value in patch code {}", code))?;
+            edits.push((addr, value));
+        }
+        return RomPatch { rom_sha1: normalize(rom_sha1), edits: Edits::Codes(edits) }.validated();
+    }
+    pub fn from_ips(ips: &[u8], rom_sha1: Option<String>) -> Result<RomPatch, String> {
+        return RomPatch { rom_sha1: normalize(rom_sha1), edits: Edits::Ips(ips.to_vec()) }.validated();
+    }
+    // BPS records the checksum of the ROM it applies to, so a hash is optional
+    pub fn from_bps(bps: &[u8], rom_sha1: Option<String>) -> Result<RomPatch, String> {
+        return RomPatch { rom_sha1: normalize(rom_sha1), edits: Edits::Bps(bps.to_vec()) }.validated();
+    }
+    pub fn get_rom_hash(&self) -> Option<String> {
+        return self.rom_sha1.clone();
+    }
+    // for a ROM loaded at 0x200
+    pub fn apply(&self, rom: &[u8]) -> Result<Vec<u8>, String> {
+        self.verify(&sha1_smol::Sha1::from(rom).digest().to_string())?;
+        return self.apply_edits(rom, START_OF_PROG);
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Patch is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<RomPatch, String> {
+        let patch: RomPatch = serde_json::from_str(json).map_err(|e| format!("Invalid patch: {}", e))?;
+        return patch.validated();
+    }
+}
+
+impl RomPatch {
+    // the checks every way of making a patch goes through, so bad files are
+    // caught when added rather than on every load
+    fn validated(self) -> Result<RomPatch, String> {
+        match &self.edits {
+            Edits::Codes(codes) => {
+                if let Some((addr, _)) = codes.iter().find(|(addr, _)| *addr >= MEM_SIZE){
+                    return Err(format!("Patch code for {:X} is outside memory", addr));
+                }
+            }
+            Edits::Ips(ips) => {
+                if !ips.starts_with(b"PATCH"){
+                    return Err("Not an IPS patch".to_string());
+                }
+                apply_ips(ips, &[], 0)?;
+            }
+            Edits::Bps(bps) => {
+                if bps.len() < 16 || !bps.starts_with(b"BPS1"){
+                    return Err("Not a BPS patch".to_string());
+                }
+                let body = bps.len() - 4;
+                if crc32(&bps[..body]) != u32::from_le_bytes(bps[body..].try_into().unwrap()){
+                    return Err("BPS patch is corrupt".to_string());
+                }
+            }
+        }
+        return Ok(self);
+    }
+    fn verify(&self, rom_sha1: &str) -> Result<(), String> {
+        return match &self.rom_sha1 {
+            Some(expected) if expected != rom_sha1 => Err(format!("Patch is for ROM {} but {} is loaded", expected, rom_sha1)),
+            _ => Ok(())
+        };
+    }
+    fn apply_edits(&self, rom: &[u8], load_address: usize) -> Result<Vec<u8>, String> {
+        return match &self.edits {
+            Edits::Codes(codes) => {
+                let mut out = rom.to_vec();
+                for (addr, value) in codes.iter(){
+                    let offset = addr.checked_sub(load_address).ok_or(format!("Patch code for {:03X} is before the program", addr))?;
+                    if offset >= out.len(){
+                        out.resize(offset + 1, 0);
+                    }
+                    out[offset] = *value;
+                }
+                Ok(out)
+            }
+            Edits::Ips(ips) => apply_ips(ips, rom, load_address),
+            Edits::Bps(bps) => apply_bps(bps, rom, load_address)
+        };
+    }
+}
+
+fn normalize(sha1: Option<String>) -> Option<String> {
+    return sha1.map(|s| s.to_ascii_lowercase());
+}
+
+// CRC-32 as used by BPS and zip
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for byte in bytes{
+        crc ^= *byte as u32;
+        for _ in 0..8{
+            crc = if crc & 1 == 1 {(crc >> 1) ^ 0xEDB88320} else {crc >> 1};
+        }
+    }
+    return !crc;
+}
+
+// IPS offsets count from the start of the ROM, which sits at `load_address`
+fn apply_ips(ips: &[u8], rom: &[u8], load_address: usize) -> Result<Vec<u8>, String> {
+    let truncated = || "IPS patch is truncated".to_string();
+    let mut out = rom.to_vec();
+    let mut pos = 5;
+    loop {
+        let record = ips.get(pos..pos + 3).ok_or_else(truncated)?;
+        if record == b"EOF"{
+            pos += 3;
+            break;
+        }
+        let offset = u32::from_be_bytes([0, record[0], record[1], record[2]]) as usize;
+        let size = ips.get(pos + 3..pos + 5).ok_or_else(truncated)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+        pos += 5;
+        let data = if size == 0 {
+            // run length encoded: a count and the byte to repeat
+            let run = ips.get(pos..pos + 3).ok_or_else(truncated)?;
+            pos += 3;
+            vec![run[2]; u16::from_be_bytes([run[0], run[1]]) as usize]
+        } else {
+            let data = ips.get(pos..pos + size).ok_or_else(truncated)?.to_vec();
+            pos += size;
+            data
+        };
+        if offset + data.len() > MEM_SIZE.saturating_sub(load_address){
+            return Err("IPS patch writes past the end of memory".to_string());
+        }
+        if out.len() < offset + data.len(){
+            out.resize(offset + data.len(), 0);
+        }
+        out[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    // an optional final length the output is cut to
+    if let Some(len) = ips.get(pos..pos + 3){
+        out.truncate(u32::from_be_bytes([0, len[0], len[1], len[2]]) as usize);
+    }
+    return Ok(out);
+}
+
+// reads the variable length numbers BPS is made of
+struct BpsReader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl BpsReader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.pos).ok_or("BPS patch is truncated")?;
+        self.pos += 1;
+        return Ok(byte);
+    }
+    fn number(&mut self) -> Result<usize, String> {
+        let (mut data, mut shift) = (0_usize, 1_usize);
+        loop {
+            let byte = self.byte()?;
+            let part = ((byte & 0x7F) as usize).checked_mul(shift).ok_or("BPS patch is corrupt")?;
+            data = data.checked_add(part).ok_or("BPS patch is corrupt")?;
+            if byte & 0x80 != 0{
+                return Ok(data);
+            }
+            shift = shift.checked_mul(0x80).ok_or("BPS patch is corrupt")?;
+            data = data.checked_add(shift).ok_or("BPS patch is corrupt")?;
+        }
+    }
+    // relative offsets store their sign in the low bit
+    fn offset(&mut self, base: usize) -> Result<usize, String> {
+        let data = self.number()?;
+        let delta = (data >> 1) as isize * if data & 1 == 1 {-1} else {1};
+        return base.checked_add_signed(delta).ok_or("BPS patch is corrupt".to_string());
+    }
+}
+
+fn apply_bps(bps: &[u8], rom: &[u8], load_address: usize) -> Result<Vec<u8>, String> {
+    if bps.len() < 16{
+        return Err("Not a BPS patch".to_string());
+    }
+    let footer = bps.len() - 12;
+    let source_crc = u32::from_le_bytes(bps[footer..footer + 4].try_into().unwrap());
+    let target_crc = u32::from_le_bytes(bps[footer + 4..footer + 8].try_into().unwrap());
+    if crc32(rom) != source_crc{
+        return Err("BPS patch does not match this ROM".to_string());
+    }
+    let mut reader = BpsReader { bytes: &bps[..footer], pos: 4 };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata = reader.number()?;
+    if source_size != rom.len() || target_size > MEM_SIZE.saturating_sub(load_address){
+        return Err("BPS patch does not match this ROM".to_string());
+    }
+    reader.pos = reader.pos.checked_add(metadata).ok_or("BPS patch is corrupt")?;
+    let mut out: Vec<u8> = Vec::with_capacity(target_size);
+    let (mut source_rel, mut target_rel) = (0, 0);
+    while reader.pos < footer{
+        let command = reader.number()?;
+        let len = (command >> 2) + 1;
+        if out.len() + len > target_size{
+            return Err("BPS patch is corrupt".to_string());
+        }
+        match command & 3 {
+            // SourceRead, the unchanged bytes at the same offset
+            0 => out.extend_from_slice(rom.get(out.len()..out.len() + len).ok_or("BPS patch is corrupt")?),
+            // TargetRead, new bytes
+            1 => {
+                for _ in 0..len{
+                    let byte = reader.byte()?;
+                    out.push(byte);
+                }
+            }
+            // SourceCopy, bytes from elsewhere in the ROM
+            2 => {
+                source_rel = reader.offset(source_rel)?;
+                out.extend_from_slice(rom.get(source_rel..source_rel + len).ok_or("BPS patch is corrupt")?);
+                source_rel += len;
+            }
+            // TargetCopy, bytes already written, which may overlap the output
+            _ => {
+                target_rel = reader.offset(target_rel)?;
+                for _ in 0..len{
+                    let byte = *out.get(target_rel).ok_or("BPS patch is corrupt")?;
+                    out.push(byte);
+                    target_rel += 1;
+                }
+            }
+        }
+    }
+    if out.len() != target_size || crc32(&out) != target_crc{
+        return Err("BPS patch produced the wrong ROM".to_string());
+    }
+    return Ok(out);
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // The patch is checked against the loaded ROM and the program restarts
+    // with it. `get_rom_hash` keeps naming the unpatched ROM.
+    pub fn add_patch(&mut self, patch: &RomPatch) -> Result<(), String> {
+        let mut patches = self.patches.clone();
+        patches.push(patch.clone());
+        patched(&self.rom, &self.rom_hash, self.load_address, &patches)?;
+        self.patches = patches;
+        self.reset();
+        return Ok(());
+    }
+    pub fn clear_patches(&mut self){
+        self.patches.clear();
+        self.reset();
+    }
+    pub fn get_patch_count(&self) -> usize {
+        return self.patches.len();
+    }
+}
+
+// `rom` with every patch applied in turn. Hashes name the unpatched ROM,
+// so patches for the same ROM can be stacked.
+pub(crate) fn patched(rom: &[u8], rom_sha1: &str, load_address: usize, patches: &[RomPatch]) -> Result<Vec<u8>, String> {
+    let mut image = rom.to_vec();
+    for patch in patches{
+        patch.verify(rom_sha1)?;
+        image = patch.apply_edits(&image, load_address)?;
+    }
+    return Ok(image);
+}
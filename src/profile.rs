@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE, START_OF_PROG};
+use crate::keymap::Keymap;
+use crate::patch;
+use crate::quirks::Quirks;
+
+use wasm_bindgen::prelude::*;
+
+
+// Per-ROM settings the frontend persists between sessions
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomProfile {
+    quirks: Quirks,
+    keymap: Keymap,
+    // 0x200 unless set, 0x600 for ETI-660 programs
+    load_address: Option<usize>,
+    // the load address unless set
+    entry_point: Option<usize>
+}
+
+// a profile missing its keymap keeps the keyboard usable
+impl Default for RomProfile {
+    fn default() -> RomProfile {
+        return RomProfile { quirks: Quirks::default(), keymap: Keymap::standard(), load_address: None, entry_point: None };
+    }
+}
+
+#[wasm_bindgen]
+impl RomProfile {
+    pub fn new() -> RomProfile {
+        return RomProfile::default();
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        return self.quirks;
+    }
+    pub fn set_quirks(&mut self, quirks: Quirks){
+        self.quirks = quirks;
+    }
+    pub fn get_keymap(&self) -> Keymap {
+        return self.keymap.clone();
+    }
+    pub fn set_keymap(&mut self, keymap: Keymap){
+        self.keymap = keymap;
+    }
+    pub fn get_load_address(&self) -> usize {
+        return self.load_address.unwrap_or(START_OF_PROG);
+    }
+    pub fn set_load_address(&mut self, addr: usize){
+        self.load_address = Some(addr);
+    }
+    pub fn get_entry_point(&self) -> usize {
+        return self.entry_point.unwrap_or(self.get_load_address());
+    }
+    pub fn set_entry_point(&mut self, addr: usize){
+        self.entry_point = Some(addr);
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Profile is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<RomProfile, String> {
+        return serde_json::from_str(json).map_err(|e| format!("Invalid profile: {}", e));
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_load_address(&self) -> usize {
+        return self.load_address;
+    }
+    pub fn get_entry_point(&self) -> usize {
+        return self.entry_point;
+    }
+    // reloads the ROM at `load_address` and restarts at `entry_point`
+    pub fn set_memory_layout(&mut self, load_address: usize, entry_point: usize) -> Result<(), String> {
+        if load_address >= MEM_SIZE || entry_point >= MEM_SIZE{
+            return Err(format!("Layout {:03X}/{:03X} is outside memory", load_address, entry_point));
+        }
+        // patch codes name memory addresses, so moving the program can
+        // leave them behind
+        patch::patched(&self.rom, &self.rom_hash, load_address, &self.patches)?;
+        self.load_address = load_address;
+        self.entry_point = entry_point;
+        self.reset();
+        return Ok(());
+    }
+    // the keymap is applied by the frontend
+    pub fn apply_profile(&mut self, profile: &RomProfile) -> Result<(), String> {
+        self.set_quirks(profile.get_quirks());
+        if (profile.get_load_address(), profile.get_entry_point()) != (self.load_address, self.entry_point){
+            self.set_memory_layout(profile.get_load_address(), profile.get_entry_point())?;
+        }
+        return Ok(());
+    }
+}
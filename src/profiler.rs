@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+use crate::instructions::Instruction;
+
+use wasm_bindgen::prelude::*;
+
+
+// Execution counts gathered while profiling is on
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Profiler {
+    cycles: u64,
+    // opcode pattern such as 8XY4 -> executions
+    opcodes: HashMap<&'static str, u64>,
+    // per address
+    addresses: Vec<u64>,
+    // entry address -> (calls, cycles from 2NNN to the matching 00EE)
+    subroutines: HashMap<usize, (u64, u64)>,
+    // entry address and cycle count at the call, innermost last
+    active: Vec<(usize, u64)>
+}
+
+impl Profiler {
+    fn new() -> Profiler {
+        return Profiler { addresses: vec![0; MEM_SIZE], ..Profiler::default() };
+    }
+    // the call stack was replaced, so pending calls will never return
+    pub(crate) fn forget_calls(&mut self){
+        self.active.clear();
+    }
+}
+
+// The pattern an instruction is listed under, by operation nibble and the
+// sub-op that selects what it does
+fn opcode_family(instr: &Instruction) -> &'static str {
+    return match (instr.operation, instr.nnn, instr.n, instr.nn) {
+        (0x0, 0x0E0, _, _) => "00E0",
+        (0x0, 0x0EE, _, _) => "00EE",
+        (0x0, _, _, _) => "0NNN",
+        (0x1, _, _, _) => "1NNN",
+        (0x2, _, _, _) => "2NNN",
+        (0x3, _, _, _) => "3XNN",
+        (0x4, _, _, _) => "4XNN",
+        (0x5, _, _, _) => "5XY0",
+        (0x6, _, _, _) => "6XNN",
+        (0x7, _, _, _) => "7XNN",
+        (0x8, _, 0x0, _) => "8XY0",
+        (0x8, _, 0x1, _) => "8XY1",
+        (0x8, _, 0x2, _) => "8XY2",
+        (0x8, _, 0x3, _) => "8XY3",
+        (0x8, _, 0x4, _) => "8XY4",
+        (0x8, _, 0x5, _) => "8XY5",
+        (0x8, _, 0x6, _) => "8XY6",
+        (0x8, _, 0x7, _) => "8XY7",
+        (0x8, _, 0xE, _) => "8XYE",
+        (0x8, _, _, _) => "8XY?",
+        (0x9, _, _, _) => "9XY0",
+        (0xA, _, _, _) => "ANNN",
+        (0xB, _, _, _) => "BNNN",
+        (0xC, _, _, _) => "CXNN",
+        (0xD, _, _, _) => "DXYN",
+        (0xE, _, _, 0x9E) => "EX9E",
+        (0xE, _, _, 0xA1) => "EXA1",
+        (0xE, _, _, _) => "EX??",
+        (0xF, _, _, 0x07) => "FX07",
+        (0xF, _, _, 0x0A) => "FX0A",
+        (0xF, _, _, 0x15) => "FX15",
+        (0xF, _, _, 0x18) => "FX18",
+        (0xF, _, _, 0x1E) => "FX1E",
+        (0xF, _, _, 0x29) => "FX29",
+        (0xF, _, _, 0x33) => "FX33",
+        (0xF, _, _, 0x55) => "FX55",
+        (0xF, _, _, 0x65) => "FX65",
+        _ => "FX??"
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpcodeCount {
+    pub opcode: String,
+    pub count: u64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressCount {
+    pub addr: usize,
+    pub count: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubroutineProfile {
+    pub addr: usize,
+    // nearest label, or the address when there are no symbols
+    pub name: String,
+    pub calls: u64,
+    // including the subroutines it calls
+    pub cycles: u64
+}
+
+// Everything sorted busiest first
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileReport {
+    cycles: u64,
+    opcodes: Vec<OpcodeCount>,
+    addresses: Vec<AddressCount>,
+    subroutines: Vec<SubroutineProfile>
+}
+
+#[wasm_bindgen]
+impl ProfileReport {
+    pub fn get_cycles(&self) -> u64 {
+        return self.cycles;
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Profile report is always serializable");
+    }
+}
+
+impl ProfileReport {
+    pub fn opcodes(&self) -> &[OpcodeCount] {
+        return &self.opcodes;
+    }
+    pub fn addresses(&self) -> &[AddressCount] {
+        return &self.addresses;
+    }
+    pub fn subroutines(&self) -> &[SubroutineProfile] {
+        return &self.subroutines;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    // like access tracking, profiling makes `run` step one instruction at a time
+    pub fn set_profiling(&mut self, enabled: bool){
+        if enabled != self.profiler.is_some(){
+            self.profiler = if enabled {Some(Box::new(Profiler::new()))} else {None};
+        }
+    }
+    pub fn get_profiling(&self) -> bool {
+        return self.profiler.is_some();
+    }
+    pub fn clear_profile(&mut self){
+        if let Some(profiler) = self.profiler.as_mut(){
+            **profiler = Profiler::new();
+        }
+    }
+    // subroutines still running are left out until they return
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        let profiler = self.profiler.as_ref()?;
+        let mut opcodes: Vec<OpcodeCount> = profiler.opcodes.iter()
+            .map(|(opcode, count)| OpcodeCount { opcode: opcode.to_string(), count: *count })
+            .collect();
+        opcodes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.opcode.cmp(&b.opcode)));
+        let mut addresses: Vec<AddressCount> = profiler.addresses.iter().enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(addr, count)| AddressCount { addr, count: *count })
+            .collect();
+        addresses.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.addr.cmp(&b.addr)));
+        let mut subroutines: Vec<SubroutineProfile> = profiler.subroutines.iter()
+            .map(|(addr, (calls, cycles))| SubroutineProfile { addr: *addr, name: self.symbolize(*addr), calls: *calls, cycles: *cycles })
+            .collect();
+        subroutines.sort_by(|a, b| b.cycles.cmp(&a.cycles).then_with(|| a.addr.cmp(&b.addr)));
+        return Some(ProfileReport { cycles: profiler.cycles, opcodes, addresses, subroutines });
+    }
+}
+
+impl Chip8 {
+    // called with the instruction at `pc` just before it runs
+    pub(crate) fn profile_instruction(&mut self, pc: usize, instr: &Instruction){
+        let cycles = self.cycles;
+        let depth = self.stack.len();
+        let limit = self.stack_limit;
+        let profiler = match self.profiler.as_mut() {
+            Some(profiler) => profiler,
+            None => return
+        };
+        profiler.cycles += 1;
+        *profiler.opcodes.entry(opcode_family(instr)).or_insert(0) += 1;
+        profiler.addresses[pc % MEM_SIZE] += 1;
+        if instr.operation == 0x2 && depth < limit{
+            profiler.active.push((instr.nnn as usize, cycles));
+        }
+        if instr.operation == 0x0 && instr.nnn == 0x0EE{
+            if let Some((addr, start)) = profiler.active.pop(){
+                let entry = profiler.subroutines.entry(addr).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += cycles - start + 1;
+            }
+        }
+    }
+}
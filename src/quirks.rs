@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+use wasm_bindgen::prelude::*;
+
+
+// Behaviours that differ between interpreters. The defaults are a SCHIP-like
+// baseline without BXNN: shifts act on VX, FX55/FX65 leave I alone and
+// sprites clip. The original COSMAC VIP is `Quirks::vip()`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    // FX0A completes as soon as a key is pressed instead of waiting for release
+    pub key_wait_on_press: bool,
+    // 8XY6/8XYE shift VY into VX (VIP) rather than shifting VX in place
+    pub shift_uses_vy: bool,
+    // 8XY1/8XY2/8XY3 clear VF afterwards, as the VIP's flag-clobbering ALU did
+    pub logic_resets_vf: bool,
+    // FX55/FX65 leave I pointing past the last register copied (VIP)
+    pub memory_increments_index: bool,
+    // BNNN is read as BXNN and jumps to XNN + VX (SCHIP)
+    pub jump_uses_vx: bool,
+    // sprites crossing an edge wrap around instead of being clipped (XO-CHIP)
+    pub wrap_sprites: bool
+}
+
+#[wasm_bindgen]
+impl Quirks {
+    pub fn new() -> Quirks {
+        return Quirks::default();
+    }
+    pub fn vip() -> Quirks {
+        return Quirks { shift_uses_vy: true, logic_resets_vf: true, memory_increments_index: true, ..Quirks::default() };
+    }
+    pub fn schip() -> Quirks {
+        return Quirks { jump_uses_vx: true, ..Quirks::default() };
+    }
+    pub fn xochip() -> Quirks {
+        return Quirks { shift_uses_vy: true, memory_increments_index: true, wrap_sprites: true, ..Quirks::default() };
+    }
+}
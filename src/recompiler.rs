@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use wasm_encoder::{
+    CodeSection, ExportKind, ExportSection, Function, FunctionSection, ImportSection,
+    Instruction as Op, MemArg, MemoryType, Module, TypeSection, ValType
+};
+use crate::{Chip8, FONT_OFFSET};
+use crate::instructions::Instruction;
+use crate::quirks::Quirks;
+
+use wasm_bindgen::prelude::*;
+
+
+// how often a block start has to be reached before it is compiled
+pub const JIT_THRESHOLD: u32 = 16;
+const MAX_BLOCK_LEN: usize = 64;
+const INDEX_OFFSET: u64 = 16;
+const DELAY_OFFSET: u64 = 20;
+const SOUND_OFFSET: u64 = 21;
+const FLAG: u64 = 0xF;
+// locals after the regs pointer parameter
+const A: u32 = 1;
+const B: u32 = 2;
+const T: u32 = 3;
+
+// The part of the machine a compiled block can touch, laid out so generated
+// code can address it from a single pointer
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JitRegs {
+    pub v: [u8; 16],
+    pub index: u32,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub padding: [u8; 2]
+}
+
+impl JitRegs {
+    pub const SIZE: usize = 24;
+    // for runtimes that do not share our linear memory
+    pub fn to_bytes(&self) -> [u8; JitRegs::SIZE] {
+        let mut bytes = [0; JitRegs::SIZE];
+        bytes[..16].copy_from_slice(&self.v);
+        bytes[16..20].copy_from_slice(&self.index.to_le_bytes());
+        bytes[20] = self.delay_timer;
+        bytes[21] = self.sound_timer;
+        return bytes;
+    }
+    pub fn from_bytes(bytes: &[u8; JitRegs::SIZE]) -> JitRegs {
+        let mut v = [0; 16];
+        v.copy_from_slice(&bytes[..16]);
+        return JitRegs {
+            v,
+            index: u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]),
+            delay_timer: bytes[20],
+            sound_timer: bytes[21],
+            padding: [0; 2]
+        }
+    }
+}
+
+// Instantiates generated modules and runs them. Each module imports
+// `env.memory` and exports `run(regs: i32)`.
+pub trait BlockRuntime {
+    fn load(&mut self, wasm: &[u8]) -> Option<usize>;
+    fn call(&mut self, handle: usize, regs: &mut JitRegs);
+}
+
+enum Entry {
+    Counting(u32),
+    Compiled { handle: usize, len: usize },
+    // `scanned` bytes from the start were looked at and did not form a block
+    Rejected { scanned: usize }
+}
+
+impl Entry {
+    fn covers(&self, start: usize, addr: usize) -> bool {
+        let span = match self {
+            Entry::Counting(_) => 0,
+            Entry::Compiled { len, .. } => len * 2,
+            Entry::Rejected { scanned } => *scanned
+        };
+        return addr >= start && addr < start + span;
+    }
+}
+
+pub(crate) struct Jit {
+    runtime: Box<dyn BlockRuntime>,
+    entries: HashMap<usize, Entry>
+}
+
+impl Jit {
+    pub(crate) fn invalidate(&mut self, addr: usize){
+        self.entries.retain(|start, entry| !entry.covers(*start, addr));
+    }
+    pub(crate) fn clear(&mut self){
+        self.entries.clear();
+    }
+}
+
+// Instructions that only touch JitRegs and fall through to the next one.
+// Anything that branches, draws, reads keys or memory, writes memory or
+// needs the RNG ends a block and is left to the interpreter.
+pub fn is_straight_line(instr: &Instruction) -> bool {
+    match instr.operation {
+        0x6 | 0x7 | 0xA => true,
+        0x8 => matches!(instr.n, 0..=7 | 0xE),
+        0xF => matches!(instr.nn, 0x07 | 0x15 | 0x18 | 0x1E | 0x29),
+        _ => false
+    }
+}
+
+// number of straight-line instructions starting at `start`
+pub fn scan_block(memory: &[u8], start: usize) -> usize {
+    let mut len = 0;
+    let mut addr = start;
+    while len < MAX_BLOCK_LEN && addr + 1 < memory.len(){
+        let instr = Instruction::from_raw(u16::from_be_bytes([memory[addr], memory[addr + 1]]));
+        if !is_straight_line(&instr){
+            break;
+        }
+        len += 1;
+        addr += 2;
+    }
+    return len;
+}
+
+fn mem(offset: u64) -> MemArg {
+    return MemArg { offset, align: 0, memory_index: 0 };
+}
+fn load_v(f: &mut Function, x: u8){
+    f.instruction(&Op::LocalGet(0));
+    f.instruction(&Op::I32Load8U(mem(x as u64)));
+}
+// store8 truncates, which gives us the u8 wrap around for free
+fn store_local(f: &mut Function, offset: u64, local: u32){
+    f.instruction(&Op::LocalGet(0));
+    f.instruction(&Op::LocalGet(local));
+    f.instruction(&Op::I32Store8(mem(offset)));
+}
+
+fn emit(f: &mut Function, instr: &Instruction, quirks: Quirks){
+    let x = instr.x as u64;
+    match instr.operation {
+        0x6 => {
+            // 6XNN
+            f.instruction(&Op::LocalGet(0));
+            f.instruction(&Op::I32Const(instr.nn as i32));
+            f.instruction(&Op::I32Store8(mem(x)));
+        }
+        0x7 => {
+            // 7XNN
+            load_v(f, instr.x);
+            f.instruction(&Op::I32Const(instr.nn as i32));
+            f.instruction(&Op::I32Add);
+            f.instruction(&Op::LocalSet(T));
+            store_local(f, x, T);
+        }
+        0x8 => {
+            load_v(f, instr.x);
+            f.instruction(&Op::LocalSet(A));
+            load_v(f, instr.y);
+            f.instruction(&Op::LocalSet(B));
+            let reset: Option<&[Op]> = if quirks.logic_resets_vf {Some(&[Op::I32Const(0)])} else {None};
+            let shifted = if quirks.shift_uses_vy {B} else {A};
+            let (val, flag): (&[Op], Option<&[Op]>) = match instr.n {
+                0 => (&[Op::LocalGet(B)], None),
+                1 => (&[Op::LocalGet(A), Op::LocalGet(B), Op::I32Or], reset),
+                2 => (&[Op::LocalGet(A), Op::LocalGet(B), Op::I32And], reset),
+                3 => (&[Op::LocalGet(A), Op::LocalGet(B), Op::I32Xor], reset),
+                4 => (&[Op::LocalGet(A), Op::LocalGet(B), Op::I32Add],
+                      Some(&[Op::LocalGet(A), Op::LocalGet(B), Op::I32Add, Op::I32Const(8), Op::I32ShrU])),
+                5 => (&[Op::LocalGet(A), Op::LocalGet(B), Op::I32Sub],
+                      Some(&[Op::LocalGet(A), Op::LocalGet(B), Op::I32GeU])),
+                6 => (&[Op::LocalGet(shifted), Op::I32Const(1), Op::I32ShrU],
+                      Some(&[Op::LocalGet(shifted), Op::I32Const(1), Op::I32And])),
+                7 => (&[Op::LocalGet(B), Op::LocalGet(A), Op::I32Sub],
+                      Some(&[Op::LocalGet(B), Op::LocalGet(A), Op::I32GeU])),
+                _ => (&[Op::LocalGet(shifted), Op::I32Const(1), Op::I32Shl],
+                      Some(&[Op::LocalGet(shifted), Op::I32Const(7), Op::I32ShrU]))
+            };
+            for op in val{
+                f.instruction(op);
+            }
+            f.instruction(&Op::LocalSet(T));
+            store_local(f, x, T);
+            // VF is written after VX like the interpreter does
+            if let Some(flag) = flag{
+                for op in flag{
+                    f.instruction(op);
+                }
+                f.instruction(&Op::LocalSet(T));
+                store_local(f, FLAG, T);
+            }
+        }
+        0xA => {
+            // ANNN
+            f.instruction(&Op::LocalGet(0));
+            f.instruction(&Op::I32Const(instr.nnn as i32));
+            f.instruction(&Op::I32Store(mem(INDEX_OFFSET)));
+        }
+        _ => match instr.nn {
+            0x07 => {
+                f.instruction(&Op::LocalGet(0));
+                f.instruction(&Op::I32Load8U(mem(DELAY_OFFSET)));
+                f.instruction(&Op::LocalSet(T));
+                store_local(f, x, T);
+            }
+            0x15 | 0x18 => {
+                let timer = if instr.nn == 0x15 {DELAY_OFFSET} else {SOUND_OFFSET};
+                load_v(f, instr.x);
+                f.instruction(&Op::LocalSet(T));
+                store_local(f, timer, T);
+            }
+            0x1E => {
+                // saturating add, as the interpreter does
+                f.instruction(&Op::LocalGet(0));
+                f.instruction(&Op::LocalGet(0));
+                f.instruction(&Op::I32Load(mem(INDEX_OFFSET)));
+                f.instruction(&Op::LocalTee(A));
+                load_v(f, instr.x);
+                f.instruction(&Op::I32Add);
+                f.instruction(&Op::LocalTee(T));
+                f.instruction(&Op::I32Const(-1));
+                f.instruction(&Op::LocalGet(T));
+                f.instruction(&Op::LocalGet(A));
+                f.instruction(&Op::I32GeU);
+                f.instruction(&Op::Select);
+                f.instruction(&Op::I32Store(mem(INDEX_OFFSET)));
+            }
+            _ => {
+                // FX29
+                f.instruction(&Op::LocalGet(0));
+                load_v(f, instr.x);
+                f.instruction(&Op::I32Const(0xF));
+                f.instruction(&Op::I32And);
+                f.instruction(&Op::I32Const(5));
+                f.instruction(&Op::I32Mul);
+                f.instruction(&Op::I32Const(FONT_OFFSET as i32));
+                f.instruction(&Op::I32Add);
+                f.instruction(&Op::I32Store(mem(INDEX_OFFSET)));
+            }
+        }
+    }
+}
+
+// Translates `len` straight-line instructions at `start` into a module.
+// Quirks are baked in, so blocks have to be dropped when they change.
+pub fn compile_block(memory: &[u8], start: usize, len: usize, quirks: Quirks) -> Vec<u8> {
+    let mut types = TypeSection::new();
+    types.function([ValType::I32], []);
+    let mut imports = ImportSection::new();
+    imports.import("env", "memory", MemoryType { minimum: 1, maximum: None, memory64: false, shared: false });
+    let mut functions = FunctionSection::new();
+    functions.function(0);
+    let mut exports = ExportSection::new();
+    exports.export("run", ExportKind::Func, 0);
+
+    let mut f = Function::new([(3, ValType::I32)]);
+    for i in 0..len{
+        let addr = start + i * 2;
+        let instr = Instruction::from_raw(u16::from_be_bytes([memory[addr], memory[addr + 1]]));
+        debug_assert!(is_straight_line(&instr));
+        emit(&mut f, &instr, quirks);
+    }
+    f.instruction(&Op::End);
+    let mut code = CodeSection::new();
+    code.function(&f);
+
+    let mut module = Module::new();
+    module.section(&types).section(&imports).section(&functions).section(&exports).section(&code);
+    return module.finish();
+}
+
+// Runs generated modules through the browser's WebAssembly API, sharing our
+// own memory so `run` gets a plain pointer to the registers
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct WebAssemblyRuntime {
+    blocks: Vec<js_sys::Function>
+}
+
+#[cfg(target_arch = "wasm32")]
+impl BlockRuntime for WebAssemblyRuntime {
+    fn load(&mut self, wasm: &[u8]) -> Option<usize> {
+        use wasm_bindgen::JsCast;
+        let bytes = js_sys::Uint8Array::from(wasm);
+        let module = js_sys::WebAssembly::Module::new(&bytes.into()).ok()?;
+        let env = js_sys::Object::new();
+        js_sys::Reflect::set(&env, &"memory".into(), &wasm_bindgen::memory()).ok()?;
+        let imports = js_sys::Object::new();
+        js_sys::Reflect::set(&imports, &"env".into(), &env).ok()?;
+        let instance = js_sys::WebAssembly::Instance::new(&module, &imports).ok()?;
+        let run = js_sys::Reflect::get(&instance.exports(), &"run".into()).ok()?;
+        self.blocks.push(run.dyn_into::<js_sys::Function>().ok()?);
+        return Some(self.blocks.len() - 1);
+    }
+    fn call(&mut self, handle: usize, regs: &mut JitRegs){
+        let ptr = regs as *mut JitRegs as u32;
+        self.blocks[handle].call1(&JsValue::NULL, &JsValue::from(ptr)).expect("Compiled block trapped");
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    #[cfg(target_arch = "wasm32")]
+    pub fn enable_wasm_jit(&mut self){
+        self.set_block_runtime(Box::new(WebAssemblyRuntime::default()));
+    }
+    pub fn disable_jit(&mut self){
+        self.jit = None;
+    }
+    pub fn compiled_block_count(&self) -> usize {
+        let Some(jit) = self.jit.as_ref() else { return 0 };
+        return jit.entries.values().filter(|entry| matches!(entry, Entry::Compiled { .. })).count();
+    }
+}
+
+impl Chip8 {
+    pub fn set_block_runtime(&mut self, runtime: Box<dyn BlockRuntime>){
+        self.jit = Some(Jit { runtime, entries: HashMap::new() });
+    }
+
+    // Runs the compiled block at pc if there is one that fits in `budget`
+    // instructions, returning how many instructions it covered
+    pub(crate) fn run_compiled_block(&mut self, budget: usize) -> Option<usize> {
+        let pc = self.pc;
+        let quirks = self.quirks;
+        let memory = &self.memory;
+        let jit = self.jit.as_mut()?;
+        let entry = jit.entries.entry(pc).or_insert(Entry::Counting(0));
+        if let Entry::Counting(hits) = entry{
+            *hits += 1;
+            if *hits < JIT_THRESHOLD{
+                return None;
+            }
+            let len = scan_block(memory, pc);
+            let handle = if len >= 2 {jit.runtime.load(&compile_block(memory, pc, len, quirks))} else {None};
+            *entry = match handle {
+                Some(handle) => Entry::Compiled { handle, len },
+                None => Entry::Rejected { scanned: (len + 1) * 2 }
+            };
+        }
+        let (handle, len) = match entry {
+            Entry::Compiled { handle, len } if *len <= budget => (*handle, *len),
+            _ => return None
+        };
+        // pure register code cannot see the keypad, so feeding input for the
+        // whole block up front matches per instruction polling
+        for _ in 0..len{
+            self.poll_input();
+            self.cycles += 1;
+        }
+        let mut regs = JitRegs {
+            v: self.gp_reg,
+            index: self.index.min(u32::MAX as usize) as u32,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            padding: [0; 2]
+        };
+        self.jit.as_mut()?.runtime.call(handle, &mut regs);
+        self.gp_reg = regs.v;
+        self.index = regs.index as usize;
+        self.delay_timer = regs.delay_timer;
+        self.sound_timer = regs.sound_timer;
+        self.pc = pc + len * 2;
+        return Some(len);
+    }
+}
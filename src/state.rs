@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, KeyState, PixelState, DISPLAY_HEIGHT, DISPLAY_WIDTH, MEM_SIZE, PIXELS};
+use crate::analyzer::Platform;
+use crate::megachip::MegaChip;
+use crate::hash::{combine, display_hash, memory_hash, HashParts};
+use crate::variants::VariantState;
+
+use wasm_bindgen::prelude::*;
+
+
+// Snapshot of everything a running program can observe. Queued input and
+// recording/playback bookkeeping are frontend concerns and are left out.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveState {
+    rom_sha1: String,
+    pc: usize,
+    index: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+    stack: Vec<usize>,
+    display: Vec<PixelState>,
+    memory: Vec<u8>,
+    gp_reg: [u8; 16],
+    keypad: [KeyState; 16],
+    key_wait: Option<u8>,
+    rng: u64,
+    cycles: u64,
+    frame: u64,
+    // states from before the VIP variants were classic CHIP-8
+    #[serde(default)]
+    platform: Platform,
+    #[serde(default)]
+    variant: VariantState,
+    // only on MEGA-CHIP
+    #[serde(default)]
+    mega: Option<MegaChip>
+}
+
+#[wasm_bindgen]
+impl SaveState {
+    pub fn get_rom_hash(&self) -> String {
+        return self.rom_sha1.clone();
+    }
+    pub fn get_frame(&self) -> u64 {
+        return self.frame;
+    }
+    // matches `Chip8::state_hash` of the machine this was taken from
+    pub fn state_hash(&self) -> u64 {
+        return combine(HashParts {
+            pc: self.pc,
+            index: self.index,
+            stack: &self.stack,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            gp_reg: &self.gp_reg,
+            memory_hash: memory_hash(&self.memory),
+            display_hash: display_hash(&self.display),
+            platform: self.platform,
+            variant: &self.variant,
+            mega: self.mega.as_ref()
+        });
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Save state is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<SaveState, String> {
+        let mut state: SaveState = serde_json::from_str(json).map_err(|e| format!("Invalid save state: {}", e))?;
+        // states from before the 64 row display only hold the top half
+        if state.display.len() == DISPLAY_WIDTH * DISPLAY_HEIGHT{
+            state.display.resize(PIXELS, PixelState::OFF);
+        }
+        if state.memory.len() != MEM_SIZE || state.display.len() != PIXELS{
+            return Err("Save state has the wrong memory or display size".to_string());
+        }
+        // I can drift past 0xFFF through FX1E, but nothing real gets near 64K
+        // outside MEGA-CHIP, whose 01NN NNNN loads a 24 bit I
+        let index_limit = if state.platform == Platform::MegaChip {0xFFFFFF} else {0xFFFF};
+        if state.pc > 0xFFFF || state.index > index_limit || state.stack.iter().any(|&addr| addr > 0xFFFF){
+            return Err("Save state points outside the address space".to_string());
+        }
+        if state.key_wait.is_some_and(|key| key > 0xF){
+            return Err("Save state waits on a key that does not exist".to_string());
+        }
+        state.variant.validate()?;
+        if state.mega.is_some() != (state.platform == Platform::MegaChip){
+            return Err("Save state does not match its platform".to_string());
+        }
+        if let Some(mega) = state.mega.as_ref(){
+            mega.validate()?;
+        }
+        return Ok(state);
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn save_state(&self) -> SaveState {
+        return SaveState {
+            rom_sha1: self.rom_hash.clone(),
+            pc: self.pc,
+            index: self.index,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack: self.stack.clone(),
+            display: self.display.to_vec(),
+            memory: self.memory.to_vec(),
+            gp_reg: self.gp_reg,
+            keypad: self.keypad,
+            key_wait: self.key_wait,
+            rng: self.rng,
+            cycles: self.cycles,
+            frame: self.frame,
+            platform: self.platform,
+            variant: self.variant.clone(),
+            mega: self.mega.as_deref().cloned()
+        }
+    }
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), String> {
+        if state.rom_sha1 != self.rom_hash{
+            return Err(format!("Save state belongs to ROM {} but {} is loaded", state.rom_sha1, self.rom_hash));
+        }
+        if state.platform != self.platform{
+            return Err(format!("Save state is for {:?} but the machine runs {:?}", state.platform, self.platform));
+        }
+        if state.stack.len() > self.stack_limit{
+            return Err(format!("Save state is {} calls deep but the stack holds {}", state.stack.len(), self.stack_limit));
+        }
+        self.pc = state.pc;
+        self.index = state.index;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.stack = state.stack.clone();
+        // the flag belonged to the run being rewound
+        self.stack_overflow = false;
+        if let Some(profiler) = self.profiler.as_mut(){
+            profiler.forget_calls();
+        }
+        // compiled and cached code only goes stale if memory differs
+        let memory_changed = self.memory[..] != state.memory[..];
+        self.display.copy_from_slice(&state.display);
+        self.memory.copy_from_slice(&state.memory);
+        self.gp_reg = state.gp_reg;
+        self.keypad = state.keypad;
+        self.key_wait = state.key_wait;
+        self.rng = state.rng;
+        self.cycles = state.cycles;
+        self.frame = state.frame;
+        // including the FX4F latch, which must match the restored timer
+        self.variant = state.variant.clone();
+        if let (Some(mega), Some(saved)) = (self.mega.as_mut(), state.mega.as_ref()){
+            mega.restore(saved);
+        }
+        self.rehash();
+        if memory_changed{
+            self.decode_cache.clear();
+            self.threaded.clear();
+            if let Some(jit) = self.jit.as_mut(){
+                jit.clear();
+            }
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::{Chip8, MEM_SIZE};
+
+use wasm_bindgen::prelude::*;
+
+
+// Names and source positions for the addresses of a program, as produced by
+// the assembler
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolTable {
+    // label -> address
+    labels: BTreeMap<String, u16>,
+    // instruction address -> 1-based source line
+    lines: BTreeMap<u16, u32>,
+    // address -> name of a `:breakpoint`
+    breakpoints: BTreeMap<u16, String>
+}
+
+#[wasm_bindgen]
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        return SymbolTable::default();
+    }
+    pub fn get_label(&self, name: &str) -> Option<u16> {
+        return self.labels.get(name).copied();
+    }
+    pub fn get_line(&self, addr: u16) -> Option<u32> {
+        return self.lines.get(&addr).copied();
+    }
+    pub fn label_count(&self) -> usize {
+        return self.labels.len();
+    }
+    // the nearest label at or before `addr`, e.g. `draw_paddle+4`, or the
+    // bare address when no label precedes it
+    pub fn symbolize(&self, addr: u16) -> String {
+        let nearest = self.labels.iter()
+            .filter(|(_, at)| **at <= addr)
+            .max_by_key(|(_, at)| **at);
+        return match nearest {
+            Some((name, at)) if *at == addr => name.clone(),
+            Some((name, at)) => format!("{}+{}", name, addr - at),
+            None => format!("0x{:03X}", addr)
+        };
+    }
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).expect("Symbol table is always serializable");
+    }
+    pub fn from_json(json: &str) -> Result<SymbolTable, String> {
+        return serde_json::from_str(json).map_err(|e| format!("Invalid symbol table: {}", e));
+    }
+}
+
+impl SymbolTable {
+    pub fn add_label(&mut self, name: &str, addr: u16){
+        self.labels.insert(name.to_string(), addr);
+    }
+    pub fn add_line(&mut self, addr: u16, line: u32){
+        self.lines.insert(addr, line);
+    }
+    pub fn add_breakpoint(&mut self, addr: u16, name: &str){
+        self.breakpoints.insert(addr, name.to_string());
+    }
+    pub fn labels(&self) -> &BTreeMap<String, u16> {
+        return &self.labels;
+    }
+    pub fn breakpoints(&self) -> &BTreeMap<u16, String> {
+        return &self.breakpoints;
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_symbols(&self) -> SymbolTable {
+        return self.symbols.clone();
+    }
+    // also breaks at every `:breakpoint` in the table
+    pub fn set_symbols(&mut self, symbols: &SymbolTable){
+        self.symbols = symbols.clone();
+        for addr in symbols.breakpoints.keys(){
+            self.breakpoints.insert(*addr as usize % MEM_SIZE);
+        }
+    }
+    pub fn load_symbols(&mut self, json: &str) -> Result<(), String> {
+        let symbols = SymbolTable::from_json(json)?;
+        self.set_symbols(&symbols);
+        return Ok(());
+    }
+    pub fn symbolize(&self, addr: usize) -> String {
+        return self.symbols.symbolize((addr % MEM_SIZE) as u16);
+    }
+    pub fn symbolize_pc(&self) -> String {
+        return self.symbolize(self.pc);
+    }
+    // source line of the instruction at pc
+    pub fn get_source_line(&self) -> Option<u32> {
+        return self.symbols.get_line((self.pc % MEM_SIZE) as u16);
+    }
+    // return addresses, innermost last
+    pub fn symbolize_stack(&self) -> Vec<String> {
+        return self.stack.iter().map(|addr| self.symbolize(*addr)).collect();
+    }
+
+    pub fn add_breakpoint(&mut self, addr: usize){
+        self.breakpoints.insert(addr % MEM_SIZE);
+    }
+    pub fn add_breakpoint_at(&mut self, label: &str) -> Result<usize, String> {
+        let addr = self.symbols.get_label(label).ok_or(format!("Unknown label {}", label))? as usize;
+        self.add_breakpoint(addr);
+        return Ok(addr % MEM_SIZE);
+    }
+    pub fn remove_breakpoint(&mut self, addr: usize){
+        self.breakpoints.remove(&(addr % MEM_SIZE));
+    }
+    pub fn clear_breakpoints(&mut self){
+        self.breakpoints.clear();
+    }
+    pub fn get_breakpoints(&self) -> Vec<usize> {
+        return self.breakpoints.iter().copied().collect();
+    }
+    // a `:breakpoint` shows its own name, anything else its nearest label
+    pub fn symbolize_breakpoints(&self) -> Vec<String> {
+        return self.breakpoints.iter().map(|addr| {
+            match self.symbols.breakpoints.get(&(*addr as u16)) {
+                Some(name) => name.clone(),
+                None => self.symbolize(*addr)
+            }
+        }).collect();
+    }
+    // the breakpoint the last `run` stopped at, if any
+    pub fn get_break_hit(&self) -> Option<usize> {
+        return self.break_hit;
+    }
+}
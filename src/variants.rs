@@ -0,0 +1,270 @@
+use crate::{Chip8, KeyState, PixelState, DISPLAY_HEIGHT, DISPLAY_WIDTH, HIRES_HEIGHT, MEM_SIZE, PIXELS, START_OF_PROG};
+use serde::{Deserialize, Serialize};
+use crate::analyzer::Platform;
+use crate::cache::Handler;
+use crate::hash::Fnv;
+use crate::patch;
+use crate::instructions::Instruction;
+use crate::megachip::{resolve_megachip, MEGA_HEIGHT, MEGA_WIDTH};
+
+use wasm_bindgen::prelude::*;
+
+
+// CHIP-8X needs a bigger interpreter and moves programs up a page
+const CHIP8X_START: usize = 0x300;
+// HIRES programs open with a jump over the interpreter's own display code,
+// which the emulator does natively
+const HIRES_BOOT: u16 = 0x260;
+const HIRES_START: usize = 0x2C0;
+// CHIP-8X colours one 8 pixel wide column per row
+const COLOR_COLUMNS: usize = 8;
+
+// State only the VIP variants use
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct VariantState {
+    // CHIP-8X background, cycled by 02A0: blue, black, green, red
+    background: u8,
+    // CHIP-8X foreground colour 0-7 for each column of each row
+    colors: Vec<u8>,
+    // CHIP-8X second keypad
+    keypad2: [KeyState; 16],
+    // last byte sent to an output port by CHIP-8X FXF8 or CHIP-8E FX03
+    port_out: u8,
+    // CHIP-8E FX4F has set the delay timer and is waiting for it to run out
+    delay_wait: bool
+}
+
+impl Default for VariantState {
+    fn default() -> VariantState {
+        return VariantState {
+            background: 0,
+            // white until a program picks colours
+            colors: vec![7; COLOR_COLUMNS * DISPLAY_HEIGHT],
+            keypad2: [KeyState::OFF; 16],
+            port_out: 0,
+            delay_wait: false
+        };
+    }
+}
+
+impl VariantState {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.colors.len() != COLOR_COLUMNS * DISPLAY_HEIGHT || self.background > 3 || self.colors.iter().any(|c| *c > 7){
+            return Err("Save state has invalid CHIP-8X colours".to_string());
+        }
+        return Ok(());
+    }
+    pub(crate) fn hash_into(&self, fnv: &mut Fnv){
+        fnv.write(&[self.background, self.port_out, self.delay_wait as u8]);
+        fnv.write(&self.colors);
+        for key in self.keypad2.iter(){
+            fnv.write(&[*key as u8]);
+        }
+    }
+}
+
+impl Platform {
+    // variants with opcodes of their own; SCHIP and XO-CHIP programs run on
+    // the classic instruction set here
+    pub(crate) fn has_own_opcodes(&self) -> bool {
+        return matches!(self, Platform::Chip8X | Platform::Chip8E | Platform::HiresChip8 | Platform::MegaChip);
+    }
+}
+
+fn default_start(platform: Platform) -> usize {
+    return if platform == Platform::Chip8X {CHIP8X_START} else {START_OF_PROG};
+}
+
+// The handler for an instruction the platform changes or adds, if any
+pub(crate) fn resolve_variant(platform: Platform, instr: &Instruction) -> Option<Handler> {
+    let handler: Handler = match (platform, instr.operation) {
+        (Platform::Chip8X, 0x0) if instr.nnn == 0x2A0 => Chip8::cycle_background,
+        (Platform::Chip8X, 0x5) if instr.n == 1 => Chip8::add_nibbles,
+        (Platform::Chip8X, 0xB) => Chip8::set_colors,
+        (Platform::Chip8X, 0xE) if matches!(instr.nn, 0xF2 | 0xF5) => Chip8::skip_key2,
+        (Platform::Chip8X, 0xF) if matches!(instr.nn, 0xF8 | 0xFB) => Chip8::port,
+        (Platform::Chip8E, 0x0) if matches!(instr.nnn, 0x0ED | 0x0F2 | 0x151 | 0x188) => Chip8::control,
+        (Platform::Chip8E, 0x5) if matches!(instr.n, 1..=3) => Chip8::register_range,
+        (Platform::Chip8E, 0xB) if matches!(instr.x, 0xB | 0xF) => Chip8::branch,
+        (Platform::Chip8E, 0xF) if matches!(instr.nn, 0x03 | 0x1B | 0x4F | 0xE3 | 0xE7) => Chip8::misc_8e,
+        (Platform::HiresChip8, 0x0) if instr.nnn == 0x230 => Chip8::clear_hires,
+        (Platform::HiresChip8, 0x1) if instr.nnn == HIRES_BOOT => Chip8::hires_boot,
+        (Platform::MegaChip, _) => return resolve_megachip(instr),
+        _ => return None
+    };
+    return Some(handler);
+}
+
+#[wasm_bindgen]
+impl Chip8 {
+    pub fn get_platform(&self) -> Platform {
+        return self.platform;
+    }
+    // Restarts the program. A program at the old platform's usual address
+    // moves to the new one's, 0x300 on CHIP-8X; a layout chosen by a profile
+    // stays put.
+    pub fn set_platform(&mut self, platform: Platform){
+        let old = default_start(self.platform);
+        let start = default_start(platform);
+        let patches_fit = patch::patched(&self.rom, &self.rom_hash, start, &self.patches).is_ok();
+        if (self.load_address, self.entry_point) == (old, old) && patches_fit{
+            self.load_address = start;
+            self.entry_point = start;
+        }
+        self.platform = platform;
+        self.reset();
+    }
+    // MEGA-CHIP mode draws to the RGBA framebuffer instead
+    pub fn get_display_width(&self) -> usize {
+        return if self.get_megachip_mode() {MEGA_WIDTH} else {DISPLAY_WIDTH};
+    }
+    pub fn get_display_height(&self) -> usize {
+        return if self.get_megachip_mode() {MEGA_HEIGHT} else {self.display_height()};
+    }
+    pub fn get_background_color(&self) -> u8 {
+        return self.variant.background;
+    }
+    // CHIP-8X foreground colour per 8 pixel column, row by row
+    pub fn get_foreground_colors(&self) -> Vec<u8> {
+        return self.variant.colors.clone();
+    }
+    pub fn set_keypad2_state(&mut self, idx: usize, state: KeyState){
+        self.variant.keypad2[idx & 0xF] = state;
+    }
+    pub fn get_port_output(&self) -> u8 {
+        return self.variant.port_out;
+    }
+}
+
+impl Chip8 {
+    pub(crate) fn display_height(&self) -> usize {
+        return if self.platform == Platform::HiresChip8 {HIRES_HEIGHT} else {DISPLAY_HEIGHT};
+    }
+
+    fn cycle_background(&mut self, _instr: Instruction){
+        // 02A0
+        self.variant.background = (self.variant.background + 1) % 4;
+    }
+    fn add_nibbles(&mut self, instr: Instruction){
+        // 5XY1, each three bit colour field is added on its own
+        let (vx, vy) = (self.gp_reg[instr.x as usize], self.gp_reg[instr.y as usize]);
+        self.gp_reg[instr.x as usize] = ((vx & 0x77) + (vy & 0x77)) & 0x77;
+    }
+    fn set_colors(&mut self, instr: Instruction){
+        // BXY0 colours whole 8x4 zones: the low nibbles of VX and VY pick the
+        // first zone, the high nibbles how many more follow. BXYN colours N
+        // rows from pixel VY in the column holding pixel VX. The colour is
+        // in V(X+1).
+        let (vx, vy) = (self.gp_reg[instr.x as usize] as usize, self.gp_reg[instr.y as usize] as usize);
+        let color = self.gp_reg[(instr.x as usize + 1) & 0xF] & 7;
+        let (columns, rows) = if instr.n == 0 {
+            let first_row = (vy & 0xF) * 4;
+            ((vx & 0xF)..=(vx & 0xF) + (vx >> 4), first_row..first_row + ((vy >> 4) + 1) * 4)
+        } else {
+            let column = (vx % 64) / 8;
+            (column..=column, (vy % 32)..(vy % 32) + instr.n as usize)
+        };
+        for row in rows.filter(|row| *row < DISPLAY_HEIGHT){
+            for column in columns.clone().filter(|column| *column < COLOR_COLUMNS){
+                self.variant.colors[row * COLOR_COLUMNS + column] = color;
+            }
+        }
+    }
+    fn skip_key2(&mut self, instr: Instruction){
+        // EXF2 and EXF5, on the second keypad
+        let pressed = self.variant.keypad2[(self.gp_reg[instr.x as usize] & 0xF) as usize] == KeyState::ON;
+        if pressed == (instr.nn == 0xF2){
+            self.pc += 2;
+        }
+    }
+    fn port(&mut self, instr: Instruction){
+        match instr.nn {
+            // FXF8, the tone generator
+            0xF8 => self.variant.port_out = self.gp_reg[instr.x as usize],
+            // FXFB, nothing is attached to the input port
+            _ => self.gp_reg[instr.x as usize] = 0
+        }
+    }
+
+    fn control(&mut self, instr: Instruction){
+        match instr.nnn {
+            // 00ED stops the program
+            0x0ED => self.pc -= 2,
+            // 0151 waits for the delay timer
+            0x151 if self.delay_timer > 0 => self.pc -= 2,
+            // 0188 skips the next instruction
+            0x188 => self.pc += 2,
+            // 00F2 does nothing, nor does 0151 once the timer is out
+            _ => {}
+        }
+    }
+    fn register_range(&mut self, instr: Instruction){
+        let (x, y) = (instr.x as usize, instr.y as usize);
+        match instr.n {
+            // 5XY1 skips if VX > VY
+            1 => {
+                if self.gp_reg[x] > self.gp_reg[y]{
+                    self.pc += 2;
+                }
+            }
+            // 5XY2 and 5XY3 save and load VX through VY at I
+            2 => {
+                for (i, reg) in (x..=y).enumerate(){
+                    self.write_memory(self.index + i, self.gp_reg[reg]);
+                }
+                self.advance_range(x, y);
+            }
+            _ => {
+                for (i, reg) in (x..=y).enumerate(){
+                    self.gp_reg[reg] = self.memory[(self.index + i) % MEM_SIZE];
+                }
+                self.advance_range(x, y);
+            }
+        }
+    }
+    fn advance_range(&mut self, x: usize, y: usize){
+        if self.quirks.memory_increments_index && y >= x{
+            self.index = (self.index + y - x + 1) & 0xFFFF;
+        }
+    }
+    fn branch(&mut self, instr: Instruction){
+        // BBNN and BFNN branch back or forward NN bytes from this instruction
+        let here = self.pc - 2;
+        self.pc = if instr.x == 0xB {here.wrapping_sub(instr.nn as usize)} else {here + instr.nn as usize};
+    }
+    fn misc_8e(&mut self, instr: Instruction){
+        let x = instr.x as usize;
+        match instr.nn {
+            // FX03 writes VX to port 3
+            0x03 => self.variant.port_out = self.gp_reg[x],
+            // FX1B skips VX bytes
+            0x1B => self.pc += self.gp_reg[x] as usize,
+            // FX4F sets the delay timer and waits for it to run out
+            0x4F => {
+                if !self.variant.delay_wait{
+                    self.delay_timer = self.gp_reg[x];
+                    self.variant.delay_wait = true;
+                }
+                if self.delay_timer > 0{
+                    self.pc -= 2;
+                }
+                else{
+                    self.variant.delay_wait = false;
+                }
+            }
+            // FXE3 and FXE7 read port 3, where nothing is attached
+            _ => self.gp_reg[x] = 0
+        }
+    }
+
+    fn clear_hires(&mut self, _instr: Instruction){
+        // 0230
+        for i in 0..PIXELS{
+            self.set_pixel(i, PixelState::OFF);
+        }
+    }
+    fn hires_boot(&mut self, instr: Instruction){
+        // 1260 at the very start enters the program proper
+        self.pc = if self.pc - 2 == START_OF_PROG {HIRES_START} else {instr.nnn as usize};
+    }
+}
@@ -0,0 +1,79 @@
+use chip8_emulator::analyzer::{analyze, EvidenceKind, Platform};
+use chip8_emulator::quirks::Quirks;
+
+const IBM: &[u8] = include_bytes!("../static/roms/ibm.ch8");
+const SNAKE: &[u8] = include_bytes!("../static/roms/snake.ch8");
+
+#[test]
+fn plain_rom_has_no_evidence(){
+    let analysis = analyze(IBM);
+    assert!(analysis.evidence().is_empty());
+    assert_eq!(analysis.get_platform(), Platform::Chip8);
+    // nothing depends on the VIP quirks, so only the VF reset is suggested
+    assert_eq!(analysis.get_quirks(), Quirks { logic_resets_vf: true, ..Quirks::default() });
+    assert_eq!(analysis.get_reachable(), 21);
+}
+
+#[test]
+fn superchip_opcodes_pick_the_platform(){
+    let analysis = analyze(SNAKE);
+    assert!(analysis.has(EvidenceKind::SuperChipOpcode));
+    assert_eq!(analysis.get_platform(), Platform::SuperChip);
+    // no BNNN, so the jump quirk does not matter
+    assert_eq!(analysis.get_quirks(), Quirks::default());
+}
+
+#[test]
+fn quirk_sensitive_code_is_reported(){
+    let rom = [
+        0x81, 0x26, // 0x200: V1 = V2 >> 1
+        0x83, 0x3E, // 0x202: V3 <<= 1, same register so no evidence
+        0xA3, 0x00, // 0x204: I = 0x300
+        0xF1, 0x55, // 0x206: store V0-V1
+        0xF1, 0x65, // 0x208: load V0-V1 from the same I
+        0x60, 0x3E, // 0x20A: V0 = 62
+        0x61, 0x00, // 0x20C: V1 = 0
+        0xA3, 0x10, // 0x20E: I = 0x310
+        0xD0, 0x11, // 0x210: draw 1 row at 62,0
+        0x30, 0x00, // 0x212: skip if V0 == 0
+        0xB2, 0x20, // 0x214: jump to 0x220 + V0
+        0x12, 0x16  // 0x216: loop
+    ];
+    let mut image = rom.to_vec();
+    image.resize(0x111, 0);
+    // 0x310: a sprite eight pixels wide
+    image[0x110] = 0xFF;
+    let analysis = analyze(&image);
+    let kinds: Vec<(usize, EvidenceKind)> = analysis.evidence().iter().map(|e| (e.addr, e.kind)).collect();
+    assert_eq!(kinds, [
+        (0x200, EvidenceKind::Shift),
+        (0x208, EvidenceKind::IndexReuse),
+        (0x210, EvidenceKind::EdgeSprite),
+        (0x214, EvidenceKind::OffsetJump)
+    ]);
+    assert!(analysis.to_json().contains("IndexReuse"));
+    assert_eq!(analysis.get_quirks(), Quirks { wrap_sprites: true, ..Quirks::vip() });
+}
+
+#[test]
+fn narrow_sprite_at_the_edge_is_not_reported(){
+    // V0 = 63, I = 0x206, draw the single pixel 0x80 there
+    let rom = [0x60, 0x3F, 0xA2, 0x08, 0xD0, 0x01, 0x12, 0x06, 0x80];
+    assert!(!analyze(&rom).has(EvidenceKind::EdgeSprite));
+}
+
+#[test]
+fn unreachable_code_is_ignored(){
+    // jump over a shift that can never run
+    let rom = [0x12, 0x04, 0x81, 0x26, 0x12, 0x04];
+    let analysis = analyze(&rom);
+    assert!(analysis.evidence().is_empty());
+    assert_eq!(analysis.get_reachable(), 2);
+}
+
+#[test]
+fn wide_schip_sprite_at_the_edge_is_reported(){
+    // V0 = 56, V1 = 0, draw a 16x16 sprite from an unknown I
+    let rom = [0x60, 0x38, 0x61, 0x00, 0xD0, 0x10, 0x12, 0x06];
+    assert!(analyze(&rom).has(EvidenceKind::EdgeSprite));
+}
@@ -0,0 +1,174 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::assembler::assemble;
+
+fn rom(source: &str) -> Vec<u8> {
+    assemble(source).unwrap().get_rom()
+}
+
+fn run(source: &str, cycles: u32) -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(&rom(source));
+    chip8.run(cycles);
+    chip8
+}
+
+#[test]
+fn encodes_statements(){
+    let source = "
+        : main
+          clear
+          v0 := 5        v1 := v0      v2 += 3     v3 -= 1
+          v1 |= v2  v1 &= v2  v1 ^= v2  v1 += v2  v1 -= v2  v1 >>= v2  v1 =- v2  v1 <<= v2
+          v4 := random 0x0F  v5 := delay  v6 := key  delay := v7  buzzer := v8
+          i := 0x300  i += v1  i := hex v2  bcd v3  save v4  load v5
+          sprite v0 v1 5
+          if v0 == 3 then v1 := 2
+          if v0 != v1 then return
+          if v2 key then ;
+          jump0 0x300
+          native 0x123
+    ";
+    assert_eq!(rom(source), [
+        0x00, 0xE0,
+        0x60, 0x05, 0x81, 0x00, 0x72, 0x03, 0x73, 0xFF,
+        0x81, 0x21, 0x81, 0x22, 0x81, 0x23, 0x81, 0x24, 0x81, 0x25, 0x81, 0x26, 0x81, 0x27, 0x81, 0x2E,
+        0xC4, 0x0F, 0xF5, 0x07, 0xF6, 0x0A, 0xF7, 0x15, 0xF8, 0x18,
+        0xA3, 0x00, 0xF1, 0x1E, 0xF2, 0x29, 0xF3, 0x33, 0xF4, 0x55, 0xF5, 0x65,
+        0xD0, 0x15,
+        0x40, 0x03, 0x61, 0x02,
+        0x50, 0x10, 0x00, 0xEE,
+        0xE2, 0xA1, 0x00, 0xEE,
+        0xB3, 0x00,
+        0x01, 0x23
+    ]);
+}
+
+#[test]
+fn jumps_to_main_and_resolves_forward_labels(){
+    let program = assemble("
+        : draw-it
+          sprite v0 v0 1
+          return
+        : main
+          i := dot
+          draw-it
+          jump main
+        : dot
+          0x80
+    ").unwrap();
+    assert_eq!(program.get_rom(), [
+        0x12, 0x06,
+        0xD0, 0x01, 0x00, 0xEE,
+        0xA2, 0x0C, 0x22, 0x02, 0x12, 0x06,
+        0x80
+    ]);
+    let symbols = program.get_symbols();
+    assert_eq!(symbols.get_label("main"), Some(0x206));
+    assert_eq!(symbols.get_label("dot"), Some(0x20C));
+    assert_eq!(symbols.get_line(0x202), Some(3));
+    assert_eq!(symbols.get_line(0x208), Some(7));
+}
+
+#[test]
+fn comparisons_match_rust(){
+    let values = [0_u8, 1, 7, 8, 9, 127, 128, 254, 255];
+    for op in ["==", "!=", "<", ">", "<=", ">="]{
+        for &a in values.iter(){
+            for &b in values.iter(){
+                let expected = match op {
+                    "==" => a == b,
+                    "!=" => a != b,
+                    "<" => a < b,
+                    ">" => a > b,
+                    "<=" => a <= b,
+                    _ => a >= b
+                };
+                for rhs in ["v1".to_string(), b.to_string()]{
+                    let then = run(&format!(": main v0 := {} v1 := {} v2 := 0 if v0 {} {} then v2 := 1 loop again", a, b, op, rhs), 8);
+                    assert_eq!(then.get_register(2) == 1, expected, "{} {} {}", a, op, rhs);
+                    let begin = run(&format!(": main v0 := {} v1 := {} if v0 {} {} begin v2 := 1 else v2 := 2 end loop again", a, b, op, rhs), 9);
+                    assert_eq!(begin.get_register(2), if expected {1} else {2}, "{} {} {}", a, op, rhs);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn loops_and_while(){
+    // sum 1..=10 into v1
+    let chip8 = run("
+        : main
+          v0 := 0
+          v1 := 0
+          loop
+            while v0 != 10
+            v0 += 1
+            v1 += v0
+          again
+          v2 := 0xAA
+          loop again
+    ", 200);
+    assert_eq!(chip8.get_register(1), 55);
+    assert_eq!(chip8.get_register(2), 0xAA);
+}
+
+#[test]
+fn directives(){
+    let program = assemble("
+        :const SPEED 3
+        :alias counter v5
+        :calc DOUBLE { SPEED * 2 + 1 }
+        :calc RTL { 2 * 3 + 4 }
+        :macro bump reg amount { reg += amount }
+        : main
+          counter := SPEED
+          bump counter DOUBLE
+          :unpack 0xA table
+          :next target
+          v3 := 0
+          i := long table
+          :org 0x240
+        : table
+          :byte { RTL }
+          SPEED
+          :byte { @ 0x203 }
+        :assert \"table moved\" { table == 0x240 }
+    ").unwrap();
+    let rom = program.get_rom();
+    // `:calc` evaluates right to left, so DOUBLE is 3 * (2 + 1)
+    assert_eq!(&rom[..16], [
+        0x12, 0x02, 0x65, 0x03, 0x75, 0x09, 0x60, 0xA2, 0x61, 0x40, 0x63, 0x00, 0xF0, 0x00, 0x02, 0x40
+    ]);
+    assert_eq!(&rom[0x40..], [14, 3, 0x03]);
+    assert_eq!(program.get_symbols().get_label("target"), Some(0x20B));
+}
+
+#[test]
+fn reports_errors_with_lines(){
+    let error = |source: &str| assemble(source).unwrap_err();
+    assert_eq!(error(": main\n  jump nowhere"), "line 2: undefined name 'nowhere'");
+    assert_eq!(error("v0 := 1"), "line 1: the program has no 'main' label");
+    assert_eq!(error(": main\nloop\n v0 += 1"), "line 3: 'loop' is never closed");
+    assert_eq!(error(": main\n\n v0 := 300"), "line 3: expected a register or byte, found '300'");
+    assert_eq!(error(": main\n again"), "line 2: 'again' without 'loop'");
+    assert_eq!(error(": main : main"), "line 1: 'main' is already defined");
+    assert_eq!(error(": main :assert { 1 == 2 }"), "line 1: assertion failed");
+    assert_eq!(error(": main\n:org 0x1000\nloop again"), "line 3: address 1000 is out of range");
+    assert_eq!(error(":macro forever { forever }\n: main\n  forever"), "line 3: macro forever expands more than 64 levels deep");
+}
+
+#[test]
+fn hot_loads_into_a_running_chip8(){
+    let mut chip8 = Chip8::from_bytes(&rom(": main v0 := 1 loop again"));
+    chip8.set_seed(9);
+    chip8.run(10);
+    let before = chip8.get_rom_hash();
+    let symbols = chip8.load_octo(": main v0 := 2 v1 := random 0xFF loop again").unwrap();
+    assert_ne!(chip8.get_rom_hash(), before);
+    assert_eq!(chip8.get_pc(), 0x200);
+    assert_eq!(chip8.get_seed(), 9);
+    chip8.run(10);
+    assert_eq!(chip8.get_register(0), 2);
+    assert_eq!(symbols.get_label("main"), Some(0x200));
+    assert!(chip8.load_octo(": main jump").is_err());
+}
@@ -0,0 +1,81 @@
+#![allow(clippy::needless_return)]
+use chip8_emulator::Chip8;
+
+const ROMS: [&[u8]; 6] = [
+    include_bytes!("../static/roms/trip8.ch8"),
+    include_bytes!("../static/roms/life.ch8"),
+    include_bytes!("../static/roms/maze.ch8"),
+    include_bytes!("../static/roms/particles.ch8"),
+    include_bytes!("../static/roms/stars.ch8"),
+    include_bytes!("../static/roms/triangle.ch8")
+];
+
+// calls the subroutine at 0x210 (6A01), rewrites it with FX55, calls it again
+fn self_modifying(store_at: u16, regs: &[u8]) -> Vec<u8> {
+    let rom = vec![
+        0x22, 0x10,
+        0xA0 | (store_at >> 8) as u8, store_at as u8,
+        0x60, regs[0],
+        0x61, *regs.get(1).unwrap_or(&0),
+        0xF0 | (regs.len() as u8 - 1), 0x55,
+        0x22, 0x10,
+        0x12, 0x0C,
+        0x00, 0x00,
+        0x6A, 0x01,
+        0x00, 0xEE
+    ];
+    return rom;
+}
+
+#[test]
+fn rewritten_instruction_is_decoded_again(){
+    let rom = self_modifying(0x210, &[0x6A, 0x02]);
+    let mut chip8 = Chip8::from_bytes(&rom);
+    for _ in 0..12{
+        chip8.tick();
+    }
+    assert_eq!(chip8.get_register(0xA), 0x02);
+}
+
+#[test]
+fn rewritten_low_byte_is_decoded_again(){
+    let rom = self_modifying(0x211, &[0x05]);
+    let mut chip8 = Chip8::from_bytes(&rom);
+    for _ in 0..12{
+        chip8.tick();
+    }
+    assert_eq!(chip8.get_register(0xA), 0x05);
+}
+
+#[test]
+fn rewritten_byte_past_the_end_is_decoded_again(){
+    // 0x200 jumps to 0xFFF, whose 6A01 ends at 0x000, then 0x001 jumps back
+    let mut chip8 = Chip8::from_bytes(&[0x1F, 0xFF]);
+    chip8.write_mem(0xFFF, &[0x6A]);
+    chip8.write_mem(0x000, &[0x01, 0x12, 0x00]);
+    for _ in 0..3{
+        chip8.tick();
+    }
+    assert_eq!(chip8.get_register(0xA), 0x01);
+    chip8.write_mem(0x000, &[0x05]);
+    for _ in 0..3{
+        chip8.tick();
+    }
+    assert_eq!(chip8.get_register(0xA), 0x05);
+}
+
+#[test]
+fn cached_matches_uncached(){
+    for rom in ROMS{
+        let mut cached = Chip8::from_bytes(rom);
+        let mut uncached = Chip8::from_bytes(rom);
+        cached.set_seed(8);
+        uncached.set_seed(8);
+        uncached.set_decode_cache(false);
+        for _ in 0..200{
+            cached.run_frame(10);
+            uncached.run_frame(10);
+            assert_eq!(cached.state_hash(), uncached.state_hash());
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::callstack::{CHIP48_STACK_DEPTH, VIP_STACK_DEPTH};
+
+#[test]
+fn reports_frames_innermost_last(){
+    // 200: call 206, 206: call 20A, 20A: spin
+    let mut chip8 = Chip8::from_bytes(&[0x22, 0x06, 0x00, 0x00, 0x00, 0x00, 0x22, 0x0A, 0x00, 0x00, 0x12, 0x0A]);
+    assert_eq!(chip8.get_top_of_stack(), None);
+    chip8.run(2);
+    assert_eq!(chip8.get_stack_depth(), 2);
+    assert_eq!(chip8.get_top_of_stack(), Some(0x208));
+    let frames = chip8.get_call_stack();
+    assert_eq!(frames.len(), 2);
+    assert_eq!((frames[0].get_call_site(), frames[0].get_callee(), frames[0].get_return_addr()), (0x200, Some(0x206), 0x202));
+    assert_eq!((frames[1].get_call_site(), frames[1].get_callee(), frames[1].get_return_addr()), (0x206, Some(0x20A), 0x208));
+    assert_eq!(chip8.call_stack_to_json(),
+        r#"[{"call_site":512,"callee":518,"return_addr":514},{"call_site":518,"callee":522,"return_addr":520}]"#);
+}
+
+#[test]
+fn detects_overflow(){
+    // 200: call 200, forever
+    let mut chip8 = Chip8::from_bytes(&[0x22, 0x00]);
+    assert_eq!(chip8.get_stack_limit(), VIP_STACK_DEPTH);
+    chip8.run(VIP_STACK_DEPTH as u32);
+    assert!(!chip8.stack_overflowed());
+    chip8.run(1);
+    assert!(chip8.stack_overflowed());
+    assert_eq!(chip8.get_stack_depth(), VIP_STACK_DEPTH);
+
+    chip8.set_stack_limit(CHIP48_STACK_DEPTH);
+    chip8.reset();
+    assert!(!chip8.stack_overflowed());
+    chip8.run(CHIP48_STACK_DEPTH as u32 + 1);
+    assert!(chip8.stack_overflowed());
+    assert_eq!(chip8.get_stack_depth(), CHIP48_STACK_DEPTH);
+
+    // lowering the limit under the calls already made is an overflow too
+    chip8.reset();
+    chip8.run(4);
+    chip8.set_stack_limit(3);
+    assert!(chip8.stack_overflowed());
+    assert_eq!(chip8.get_stack_depth(), 4);
+}
+
+#[test]
+fn load_state_respects_the_limit(){
+    let mut chip8 = Chip8::from_bytes(&[0x22, 0x00]);
+    chip8.run(4);
+    let state = chip8.save_state();
+    chip8.run(VIP_STACK_DEPTH as u32);
+    assert!(chip8.stack_overflowed());
+    // rewinding to before the overflow clears it
+    chip8.load_state(&state).unwrap();
+    assert!(!chip8.stack_overflowed());
+    assert_eq!(chip8.get_stack_depth(), 4);
+
+    chip8.set_stack_limit(3);
+    assert!(chip8.load_state(&state).is_err());
+}
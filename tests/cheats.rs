@@ -0,0 +1,62 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::cheats::{CheatList, MemorySearch, SearchKind};
+
+// lives in V3 and at 0x300: V3 := 3, then lose one per loop, storing it
+// 200: V3 := 3, I := 300, 204: V3 -= 1, save V3 at 300+3, jump 204
+const PROGRAM: [u8; 12] = [0x63, 0x03, 0xA3, 0x00, 0x73, 0xFF, 0xF3, 0x55, 0x12, 0x04, 0x00, 0x00];
+
+fn lives_at(chip8: &Chip8) -> u8 {
+    chip8.read_mem(0x303, 1)[0]
+}
+
+#[test]
+fn finds_values_across_searches(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.run(4);
+    let mut search = MemorySearch::new(&chip8);
+    assert_eq!(lives_at(&chip8), 2);
+    let equal = search.search(&chip8, SearchKind::Equal, 2);
+    assert!(equal < 4096 && search.get_candidates().contains(&0x303));
+    chip8.run(3);
+    search.search(&chip8, SearchKind::Decreased, 0);
+    assert_eq!(search.get_candidates(), [0x303]);
+    chip8.run(3);
+    assert_eq!(search.search(&chip8, SearchKind::Unchanged, 0), 0);
+}
+
+#[test]
+fn freezes_memory_and_registers(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    let lives = chip8.add_memory_cheat("infinite lives", 0x303, 9);
+    chip8.add_register_cheat("V3", 3, 5);
+    chip8.run(50);
+    assert_eq!(lives_at(&chip8), 9);
+    assert_eq!(chip8.get_register(3), 5);
+
+    // the frozen register is what gets saved now
+    chip8.set_cheat_enabled(lives, false);
+    chip8.run(3);
+    assert_eq!(lives_at(&chip8), 5);
+    chip8.remove_cheat(1);
+    chip8.run(3);
+    assert_eq!(chip8.get_register(3), 4);
+    assert_eq!(chip8.get_cheats().get_cheats().len(), 1);
+}
+
+#[test]
+fn saves_cheats_per_rom(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.add_memory_cheat("infinite lives", 0x303, 9);
+    let json = chip8.get_cheats().to_json();
+
+    let mut same = Chip8::from_bytes(&PROGRAM);
+    same.load_cheats(&CheatList::from_json(&json).unwrap()).unwrap();
+    same.reset();
+    same.run(10);
+    assert_eq!(lives_at(&same), 9);
+
+    let mut other = Chip8::from_bytes(&[0x12, 0x00]);
+    assert!(other.load_cheats(&CheatList::from_json(&json).unwrap()).is_err());
+    let bad = json.replace("771", "4096");
+    assert!(CheatList::from_json(&bad).is_err());
+}
@@ -0,0 +1,45 @@
+use chip8_emulator::Chip8;
+
+// 200: V0 := 12, I := 300, BCD V0, load V0-V2, sprite, jump to self; 20C
+// is never reached
+const PROGRAM: [u8; 14] = [0x60, 0x0C, 0xA3, 0x00, 0xF0, 0x33, 0xF2, 0x65, 0xD0, 0x02, 0x12, 0x0A, 0x00, 0xE0];
+
+#[test]
+fn counts_accesses_when_enabled(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.run(10);
+    assert!(chip8.get_exec_counts().is_empty());
+    assert!(chip8.coverage_report().is_none());
+
+    chip8.set_access_tracking(true);
+    chip8.reset();
+    chip8.run(10);
+    let executed = chip8.get_exec_counts();
+    assert_eq!(executed.len(), 4096);
+    assert_eq!(&executed[0x200..0x20E], [1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 5, 0, 0, 0]);
+    let written = chip8.get_write_counts();
+    assert_eq!(&written[0x300..0x304], [1, 1, 1, 0]);
+    // FX65 reads three bytes, DXY2 two of them again
+    let read = chip8.get_read_counts();
+    assert_eq!(&read[0x300..0x304], [2, 2, 1, 0]);
+
+    chip8.clear_access_counts();
+    assert!(chip8.get_exec_counts().iter().all(|&c| c == 0));
+    chip8.set_access_tracking(false);
+    assert!(chip8.get_read_counts().is_empty());
+}
+
+#[test]
+fn reports_covered_instructions(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.set_access_tracking(true);
+    chip8.run(10);
+    chip8.reset();
+    chip8.run(6);
+    let report = chip8.coverage_report().unwrap();
+    assert_eq!(report.get_executed(), [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]);
+    assert!(!report.is_executed(0x20C));
+    assert_eq!(report.instructions()[5].count, 6);
+    assert_eq!(report.get_ratio(), 12.0 / 14.0);
+    assert!(report.to_json().contains(r#"{"addr":522,"count":6}"#));
+}
@@ -0,0 +1,96 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::analyzer::Platform;
+use chip8_emulator::database::RomDatabase;
+use chip8_emulator::quirks::Quirks;
+
+const ROMS: [&[u8]; 4] = [
+    include_bytes!("../static/roms/ibm.ch8"),
+    include_bytes!("../static/roms/pong.ch8"),
+    include_bytes!("../static/roms/snake.ch8"),
+    include_bytes!("../static/roms/trip8.ch8")
+];
+
+#[test]
+fn every_bundled_rom_is_known(){
+    let database = RomDatabase::builtin();
+    assert_eq!(database.len(), 17);
+    for entry in std::fs::read_dir("static/roms").unwrap(){
+        let rom = std::fs::read(entry.unwrap().path()).unwrap();
+        let chip8 = Chip8::from_bytes(&rom);
+        assert!(database.lookup(&chip8.get_rom_hash()).is_some());
+    }
+}
+
+#[test]
+fn auto_configure_applies_quirks(){
+    let mut chip8 = Chip8::from_bytes(ROMS[2]);
+    let info = chip8.auto_configure().unwrap();
+    assert_eq!(info.get_title(), "Snake");
+    assert_eq!(info.get_platform(), Platform::SuperChip);
+    assert_eq!(chip8.get_quirks(), Quirks::schip());
+    assert_eq!(chip8.get_platform(), Platform::Chip8);
+
+    // a variant chosen for an earlier ROM is dropped
+    chip8.set_platform(Platform::Chip8X);
+    chip8.load_rom(ROMS[2]);
+    assert_eq!(chip8.get_load_address(), 0x300);
+    chip8.auto_configure().unwrap();
+    assert_eq!(chip8.get_platform(), Platform::Chip8);
+    assert_eq!((chip8.get_load_address(), chip8.get_pc()), (0x200, 0x200));
+
+    let corax = Chip8::from_bytes(include_bytes!("../static/roms/corax-test.ch8")).get_rom_info().unwrap();
+    assert_eq!(corax.get_quirks(), Quirks { shift_uses_vy: true, memory_increments_index: true, ..Quirks::default() });
+
+    let mut unknown = Chip8::from_bytes(&[0x12, 0x00]);
+    assert!(unknown.auto_configure().is_none());
+    assert_eq!(unknown.get_quirks(), Quirks::default());
+}
+
+#[test]
+fn metadata_and_keys(){
+    let trip8 = Chip8::from_bytes(ROMS[3]).get_rom_info().unwrap();
+    assert_eq!(trip8.get_authors(), ["Revival Studios"]);
+    assert_eq!(trip8.get_quirks(), Quirks::vip());
+    let pong = Chip8::from_bytes(ROMS[1]).get_rom_info().unwrap();
+    let keymap = pong.get_keymap();
+    assert_eq!(keymap.map_key("ArrowUp"), Some(1));
+    assert_eq!(keymap.map_key("Numpad2"), Some(13));
+    assert_eq!(keymap.map_gamepad_button(13), Some(4));
+    assert_eq!(keymap.map_key("KeyX"), None);
+    // the profile keeps the rest of the keypad on the standard layout
+    let profile = pong.profile().get_keymap();
+    assert_eq!(profile.map_key("KeyX"), Some(0));
+    assert_eq!(profile.map_key("ArrowDown"), Some(4));
+    assert_eq!(Chip8::from_bytes(ROMS[0]).get_rom_info().unwrap().get_tick_rate(), None);
+}
+
+#[test]
+fn community_format(){
+    let json = r##"[{
+        "title": "Test",
+        "authors": ["Someone"],
+        "roms": {
+            "ABCDEF0123456789ABCDEF0123456789ABCDEF01": {
+                "file": "test.ch8",
+                "platforms": ["someFuturePlatform", "xochip"],
+                "tickrate": 1000,
+                "quirkyPlatforms": { "xochip": { "shift": true, "wrap": false, "vblank": true } },
+                "keys": { "left": 7, "right": 9 },
+                "colors": { "pixels": ["#000000", "#ffffff"], "buzzer": "#990000", "silence": "#330000" }
+            }
+        }
+    }]"##;
+    let mut database = RomDatabase::from_json(json).unwrap();
+    let info = database.lookup("abcdef0123456789abcdef0123456789abcdef01").unwrap();
+    assert_eq!(info.get_platform(), Platform::XoChip);
+    assert_eq!(info.get_quirks(), Quirks { shift_uses_vy: false, wrap_sprites: false, ..Quirks::xochip() });
+    assert_eq!(info.get_tick_rate(), Some(1000));
+    assert_eq!(info.get_pixel_colors(), ["#000000", "#ffffff"]);
+    assert_eq!(info.get_buzzer_color().as_deref(), Some("#990000"));
+    assert_eq!(info.get_keymap().map_key("ArrowLeft"), Some(7));
+
+    database.merge_json(&json.replace("\"Test\"", "\"Renamed\"")).unwrap();
+    assert_eq!(database.len(), 1);
+    assert_eq!(database.lookup("ABCDEF0123456789ABCDEF0123456789ABCDEF01").unwrap().get_title(), "Renamed");
+    assert!(RomDatabase::from_json("{}").is_err());
+}
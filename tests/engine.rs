@@ -0,0 +1,74 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::engine::Engine;
+
+const ROMS: [&[u8]; 8] = [
+    include_bytes!("../static/roms/trip8.ch8"),
+    include_bytes!("../static/roms/life.ch8"),
+    include_bytes!("../static/roms/division.ch8"),
+    include_bytes!("../static/roms/particles.ch8"),
+    include_bytes!("../static/roms/stars.ch8"),
+    include_bytes!("../static/roms/triangle.ch8"),
+    include_bytes!("../static/roms/corax-test.ch8"),
+    include_bytes!("../static/roms/snake.ch8")
+];
+
+#[test]
+fn threaded_matches_interpreter(){
+    for rom in ROMS{
+        let mut threaded = Chip8::from_bytes(rom);
+        let mut interpreter = Chip8::from_bytes(rom);
+        threaded.set_seed(4);
+        interpreter.set_seed(4);
+        threaded.set_engine(Engine::Threaded);
+        interpreter.set_decode_cache(false);
+        for step in 0..3000_u32{
+            let cycles = step % 23 + 1;
+            threaded.run(cycles);
+            interpreter.run(cycles);
+            assert_eq!(threaded.get_cycles(), interpreter.get_cycles());
+            assert_eq!(threaded.state_hash(), interpreter.state_hash());
+            if step % 10 == 0{
+                threaded.tick_timers();
+                interpreter.tick_timers();
+            }
+        }
+        assert!(threaded.threaded_block_count() > 0);
+    }
+}
+
+#[test]
+fn writes_into_code_drop_the_block(){
+    let rom = [
+        0x70, 0x01, // 0x200: V0 += 1
+        0x61, 0x05, // 0x202: V1 = 5, rewritten to V1 = 7 below
+        0x82, 0x14, // 0x204: V2 += V1
+        0x30, 0x40, // 0x206: skip if V0 == 0x40
+        0x12, 0x00, // 0x208: loop
+        0xA2, 0x02, // 0x20A: I = 0x202
+        0x60, 0x61, // 0x20C: V0 = 0x61
+        0x61, 0x07, // 0x20E: V1 = 0x07
+        0xF1, 0x55, // 0x210: store V0-V1 over 0x202
+        0x60, 0x00, // 0x212: V0 = 0
+        0x12, 0x00  // 0x214: loop
+    ];
+    let mut threaded = Chip8::from_bytes(&rom);
+    let mut interpreter = Chip8::from_bytes(&rom);
+    threaded.set_engine(Engine::Threaded);
+    for _ in 0..100{
+        threaded.run(9);
+        interpreter.run(9);
+        assert_eq!(threaded.state_hash(), interpreter.state_hash());
+    }
+    assert_eq!(threaded.get_register(1), 7);
+}
+
+#[test]
+fn engine_survives_reset(){
+    let mut chip8 = Chip8::from_bytes(ROMS[0]);
+    assert_eq!(chip8.get_engine(), Engine::Interpreter);
+    chip8.set_engine(Engine::Threaded);
+    chip8.run(100);
+    chip8.reset();
+    assert_eq!(chip8.get_engine(), Engine::Threaded);
+    assert_eq!(chip8.threaded_block_count(), 0);
+}
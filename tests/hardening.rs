@@ -0,0 +1,68 @@
+use chip8_emulator::engine::Engine;
+use chip8_emulator::state::SaveState;
+use chip8_emulator::{Chip8, KeyState};
+
+// Inputs the fuzz targets found or that target known panic paths.
+// Each only has to run without panicking.
+
+fn run_both(rom: &[u8], cycles: u32){
+    for engine in [Engine::Interpreter, Engine::Threaded]{
+        let mut chip8 = Chip8::from_bytes(rom);
+        chip8.set_engine(engine);
+        chip8.run_frame(cycles);
+    }
+}
+
+#[test]
+fn return_on_empty_stack(){
+    // 00EE
+    run_both(&[0x00, 0xEE, 0x12, 0x00], 10);
+}
+
+#[test]
+fn memory_ops_at_end_of_memory(){
+    // AFFF, FF55, FF65, FF33, D01F
+    run_both(&[0xAF, 0xFF, 0xFF, 0x55, 0xFF, 0x65, 0xFF, 0x33, 0xD0, 0x1F], 5);
+}
+
+#[test]
+fn index_past_end_of_memory(){
+    // AFFF, 60FF, F01E repeated, then FF55
+    run_both(&[0xAF, 0xFF, 0x60, 0xFF, 0xF0, 0x1E, 0xF0, 0x1E, 0xFF, 0x55, 0xD0, 0x1F], 6);
+}
+
+#[test]
+fn pc_runs_off_the_end(){
+    // 1FFE lands on the last word, execution continues into the wraparound
+    let mut rom = vec![0x1F, 0xFE];
+    rom.resize(0xE00, 0);
+    rom[0xDFE] = 0x70;
+    rom[0xDFF] = 0x01;
+    run_both(&rom, 10);
+    // BNNN past 0xFFF
+    run_both(&[0x60, 0xFF, 0xBF, 0xFF], 5);
+}
+
+#[test]
+fn skip_on_key_above_f(){
+    // 60FF, E09E, E0A1
+    let mut chip8 = Chip8::from_bytes(&[0x60, 0xFF, 0xE0, 0x9E, 0xE0, 0xA1]);
+    chip8.set_key_state(0xF, KeyState::ON);
+    chip8.run(3);
+    assert_eq!(chip8.get_pc(), 0x208);
+}
+
+#[test]
+fn oversized_rom_is_truncated(){
+    run_both(&vec![0x70; 8192], 10);
+}
+
+#[test]
+fn crafted_save_states_are_rejected(){
+    let chip8 = Chip8::from_bytes(&[0x12, 0x00]);
+    let json = chip8.save_state().to_json();
+    let far_pc = json.replace("\"pc\":512", "\"pc\":18446744073709551615");
+    assert!(SaveState::from_json(&far_pc).is_err());
+    let bad_key = json.replace("\"key_wait\":null", "\"key_wait\":200");
+    assert!(SaveState::from_json(&bad_key).is_err());
+}
@@ -0,0 +1,47 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::instructions::Instruction;
+
+const LIFE: &[u8] = include_bytes!("../static/roms/life.ch8");
+const DIVISION: &[u8] = include_bytes!("../static/roms/division.ch8");
+
+#[test]
+fn incremental_matches_full(){
+    for rom in [LIFE, DIVISION]{
+        let mut chip8 = Chip8::from_bytes(rom);
+        chip8.set_seed(11);
+        for _ in 0..100{
+            chip8.run_frame(10);
+            assert_eq!(chip8.incremental_state_hash(), chip8.state_hash());
+        }
+        assert_eq!(chip8.save_state().state_hash(), chip8.state_hash());
+    }
+}
+
+#[test]
+fn equal_states_hash_equal(){
+    let mut a = Chip8::from_bytes(LIFE);
+    let mut b = Chip8::from_bytes(LIFE);
+    assert_eq!(a.state_hash(), b.state_hash());
+    a.set_seed(5);
+    b.set_seed(5);
+    for _ in 0..20{
+        a.run_frame(10);
+        b.run_frame(10);
+    }
+    assert_eq!(a.state_hash(), b.state_hash());
+    b.set_register(Instruction::from_str("6E01"));
+    assert_ne!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn memory_writes_change_hash(){
+    let mut chip8 = Chip8::from_bytes(&[]);
+    let before = chip8.incremental_state_hash();
+    chip8.set_index(Instruction::from_str("A800"));
+    chip8.set_register(Instruction::from_str("6001"));
+    let registers_only = chip8.incremental_state_hash();
+    chip8.f(Instruction::from_str("F055"));
+    assert_ne!(chip8.incremental_state_hash(), registers_only);
+    assert_ne!(chip8.incremental_state_hash(), before);
+    assert_eq!(chip8.incremental_state_hash(), chip8.state_hash());
+}
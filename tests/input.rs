@@ -0,0 +1,48 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::KeyState;
+
+// 0x200: F00A  wait for key into V0
+// 0x202: 1202  spin
+const WAIT_FOR_KEY: [u8; 4] = [0xF0, 0x0A, 0x12, 0x02];
+
+#[test]
+fn queued_tap_is_not_lost(){
+    let mut chip8 = Chip8::from_bytes(&WAIT_FOR_KEY);
+    chip8.queue_key_event(5, KeyState::ON, 10.0);
+    chip8.queue_key_event(5, KeyState::OFF, 12.0);
+    assert_eq!(chip8.pending_key_events(), 2);
+    chip8.tick();
+    assert_eq!(chip8.get_key_state(5), KeyState::ON);
+    assert_eq!(chip8.get_pc(), 0x200);
+    chip8.tick();
+    assert_eq!(chip8.get_key_state(5), KeyState::OFF);
+    assert_eq!(chip8.get_pc(), 0x202);
+    assert_eq!(chip8.get_register(0), 5);
+    assert_eq!(chip8.pending_key_events(), 0);
+}
+
+#[test]
+fn events_are_ordered_by_timestamp(){
+    let mut chip8 = Chip8::from_bytes(&WAIT_FOR_KEY);
+    chip8.queue_key_event(3, KeyState::OFF, 20.0);
+    chip8.queue_key_event(3, KeyState::ON, 15.0);
+    chip8.tick();
+    assert_eq!(chip8.get_key_state(3), KeyState::ON);
+    chip8.tick();
+    assert_eq!(chip8.get_key_state(3), KeyState::OFF);
+    assert_eq!(chip8.get_register(0), 3);
+}
+
+#[test]
+fn held_key_blocks_until_release(){
+    let mut chip8 = Chip8::from_bytes(&WAIT_FOR_KEY);
+    chip8.queue_key_event(0xA, KeyState::ON, 0.0);
+    for _ in 0..10{
+        chip8.tick();
+    }
+    assert_eq!(chip8.get_pc(), 0x200);
+    chip8.queue_key_event(0xA, KeyState::OFF, 1.0);
+    chip8.tick();
+    assert_eq!(chip8.get_pc(), 0x202);
+    assert_eq!(chip8.get_register(0), 0xA);
+}
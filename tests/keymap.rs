@@ -0,0 +1,57 @@
+use chip8_emulator::keymap::Keymap;
+use chip8_emulator::profile::RomProfile;
+
+#[test]
+fn standard_layout(){
+    let keymap = Keymap::standard();
+    assert_eq!(keymap.map_key("Digit1"), Some(0x1));
+    assert_eq!(keymap.map_key("Digit4"), Some(0xC));
+    assert_eq!(keymap.map_key("KeyX"), Some(0x0));
+    assert_eq!(keymap.map_key("KeyV"), Some(0xF));
+    assert_eq!(keymap.map_key("KeyT"), None);
+}
+
+#[test]
+fn legacy_layout_matches_old_frontend(){
+    let keymap = Keymap::legacy();
+    assert_eq!(keymap.map_key("KeyQ"), Some(0x0));
+    assert_eq!(keymap.map_key("KeyG"), Some(0x9));
+    assert_eq!(keymap.map_key("Space"), Some(0xF));
+}
+
+#[test]
+fn gamepad_buttons(){
+    let mut keymap = Keymap::standard();
+    assert_eq!(keymap.map_gamepad_button(12), Some(0x2));
+    assert_eq!(keymap.map_gamepad_button(0), Some(0x5));
+    assert_eq!(keymap.map_gamepad_button(16), None);
+    keymap.bind_gamepad_button(16, 0x1F);
+    assert_eq!(keymap.map_gamepad_button(16), Some(0xF));
+}
+
+#[test]
+fn overrides_win(){
+    let mut overrides = Keymap::empty();
+    overrides.bind_key("KeyW", 0x2);
+    overrides.bind_gamepad_button(0, 0x6);
+    let keymap = Keymap::standard().with_overrides(&overrides);
+    assert_eq!(keymap.map_key("KeyW"), Some(0x2));
+    assert_eq!(keymap.map_key("KeyQ"), Some(0x4));
+    assert_eq!(keymap.map_gamepad_button(0), Some(0x6));
+}
+
+#[test]
+fn profile_round_trip(){
+    let mut profile = RomProfile::new();
+    let mut keymap = profile.get_keymap();
+    keymap.unbind_key("KeyQ");
+    keymap.bind_key("ArrowUp", 0x5);
+    profile.set_keymap(keymap);
+    let restored = RomProfile::from_json(&profile.to_json()).unwrap();
+    assert_eq!(restored, profile);
+    assert_eq!(restored.get_keymap().map_key("ArrowUp"), Some(0x5));
+    assert!(RomProfile::from_json("{\"keymap\": 3}").is_err());
+    // older profiles without a keymap get the standard layout
+    let old = RomProfile::from_json("{\"quirks\": {}}").unwrap();
+    assert_eq!(old.get_keymap(), Keymap::standard());
+}
@@ -0,0 +1,71 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::analyzer::analyze;
+use chip8_emulator::memory::RegionKind;
+use chip8_emulator::patch::RomPatch;
+use chip8_emulator::profile::RomProfile;
+
+// V0 := 1, jump to self, assembled for 0x600
+const ETI_ROM: [u8; 4] = [0x60, 0x01, 0x16, 0x02];
+
+#[test]
+fn defaults_to_0x200(){
+    let chip8 = Chip8::from_bytes(&ETI_ROM);
+    assert_eq!((chip8.get_load_address(), chip8.get_entry_point()), (0x200, 0x200));
+    let profile = RomProfile::new();
+    assert_eq!((profile.get_load_address(), profile.get_entry_point()), (0x200, 0x200));
+    // profiles saved before the layout existed still load
+    let old = RomProfile::from_json(r#"{"quirks": {}}"#).unwrap();
+    assert_eq!(old.get_load_address(), 0x200);
+}
+
+#[test]
+fn loads_and_starts_where_the_profile_says(){
+    let mut chip8 = Chip8::from_bytes(&ETI_ROM);
+    let mut profile = RomProfile::new();
+    profile.set_load_address(0x600);
+    assert_eq!(profile.get_entry_point(), 0x600);
+    chip8.apply_profile(&profile).unwrap();
+    assert_eq!(chip8.read_mem(0x600, 4), ETI_ROM);
+    assert_eq!(chip8.read_mem(0x200, 4), [0; 4]);
+    assert_eq!(chip8.get_pc(), 0x600);
+    chip8.run(10);
+    assert_eq!((chip8.get_register(0), chip8.get_pc()), (1, 0x602));
+
+    // survives a reset and drives the tools that look at the program
+    chip8.reset();
+    assert_eq!(chip8.get_pc(), 0x600);
+    let program = chip8.get_memory_map().into_iter().find(|r| r.get_kind() == RegionKind::Program).unwrap();
+    assert_eq!((program.get_start(), program.get_end()), (0x600, 0x604));
+    assert_eq!(chip8.analyze_rom().get_reachable(), 2);
+    // at 0x200 the jump lands in empty memory
+    assert!(analyze(&ETI_ROM).get_reachable() > 2);
+    chip8.add_patch(&RomPatch::from_codes("601:05", None).unwrap()).unwrap();
+    chip8.run(1);
+    assert_eq!(chip8.get_register(0), 5);
+
+    profile.set_entry_point(0x602);
+    chip8.apply_profile(&profile).unwrap();
+    assert_eq!(chip8.get_pc(), 0x602);
+    chip8.run(1);
+    assert_eq!(chip8.get_register(0), 0);
+
+    assert!(chip8.set_memory_layout(0x1000, 0x200).is_err());
+    chip8.set_memory_layout(0x200, 0x200).unwrap();
+    // patch codes name memory addresses, wherever the ROM sits
+    assert_eq!(chip8.read_mem(0x200, 2), [0x60, 0x01]);
+    assert_eq!(chip8.read_mem(0x601, 1), [0x05]);
+    // past 0x601 the patch would fall before the program
+    assert!(chip8.set_memory_layout(0x700, 0x700).is_err());
+    assert_eq!(chip8.get_load_address(), 0x200);
+}
+
+#[test]
+fn assembles_for_the_load_address(){
+    let mut chip8 = Chip8::from_bytes(&[]);
+    chip8.set_memory_layout(0x600, 0x600).unwrap();
+    chip8.load_octo(": main v0 := 1 jump main").unwrap();
+    assert_eq!(chip8.read_mem(0x600, 4), [0x60, 0x01, 0x16, 0x00]);
+    assert_eq!(chip8.symbolize(0x600), "main");
+    chip8.run(3);
+    assert_eq!((chip8.get_register(0), chip8.get_pc()), (1, 0x602));
+}
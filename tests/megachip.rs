@@ -0,0 +1,154 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::analyzer::Platform;
+use chip8_emulator::megachip::BlendMode;
+use chip8_emulator::state::SaveState;
+
+fn megachip(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(rom);
+    chip8.set_platform(Platform::MegaChip);
+    chip8
+}
+
+fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_le_bytes([r, g, b, a])
+}
+
+#[test]
+fn draws_colour_sprites_from_past_4k(){
+    let mut rom = vec![
+        0x00, 0x11,             // MEGA-CHIP mode
+        0x01, 0x00, 0x10, 0x00, // I := 0x1000, past the 4K address space
+        0x02, 0x02,             // two palette entries
+        0x01, 0x00, 0x10, 0x08, // I := 0x1008
+        0x03, 0x02, 0x04, 0x02, // 2x2 sprites
+        0x60, 0x0A, 0x61, 0x05, // V0 := 10, V1 := 5
+        0xD0, 0x10,
+        0x12, 0x16
+    ];
+    rom.resize(0x1000 - 0x200, 0);
+    rom.extend_from_slice(&[0xFF, 0x10, 0x20, 0x30, 0x80, 0x40, 0x50, 0x60]);
+    rom.extend_from_slice(&[0x01, 0x00, 0x02, 0x01]);
+    let mut chip8 = megachip(&rom);
+    assert!(!chip8.get_megachip_mode());
+    chip8.run(10);
+    assert!(chip8.get_megachip_mode());
+    assert_eq!((chip8.get_display_width(), chip8.get_display_height()), (256, 192));
+    assert_eq!(chip8.get_index(), 0x1008);
+    let frame = chip8.framebuffer_to_vec();
+    assert_eq!(frame.len(), 256 * 192);
+    let pixel = |x: usize, y: usize| frame[y * 256 + x];
+    assert_eq!(pixel(10, 5), rgba(0x10, 0x20, 0x30, 0xFF));
+    assert_eq!(pixel(11, 5), 0);
+    assert_eq!(pixel(10, 6), rgba(0x40, 0x50, 0x60, 0x80));
+    assert_eq!(pixel(11, 6), rgba(0x10, 0x20, 0x30, 0xFF));
+    assert_eq!(chip8.get_register(0xF), 0);
+}
+
+#[test]
+fn blending_collisions_and_sound(){
+    let mut rom = vec![
+        0x00, 0x11,
+        0xA3, 0x00, 0x02, 0x01, // palette entry 1 from 0x300
+        0xA3, 0x04, 0x03, 0x01, // 1x1 sprite at 0x304
+        0x09, 0x01,             // colour 1 collides
+        0xD0, 0x00,
+        0x08, 0x04, 0xD0, 0x00, // again, added
+        0x05, 0x80,             // screen alpha
+        0xA3, 0x08, 0x06, 0x01, // play 0x308 once
+        0x00, 0x10, 0x00, 0xE0, // back to the classic display
+        0x12, 0x1C
+    ];
+    rom.resize(0x100, 0);
+    rom.extend_from_slice(&[0xFF, 0x80, 0x20, 0xF0, 0x01, 0x00, 0x00, 0x00]);
+    rom.extend_from_slice(&[0x1F, 0x40, 0x00, 0x00, 0x03, 0x00, 0x7F, 0x80, 0x81]);
+    let mut chip8 = megachip(&rom);
+    chip8.run(7);
+    assert_eq!(chip8.framebuffer_to_vec()[0], rgba(0x80, 0x20, 0xF0, 0xFF));
+    assert_eq!(chip8.get_register(0xF), 0);
+    chip8.run(2);
+    assert_eq!(chip8.get_blend_mode(), BlendMode::Add);
+    assert_eq!(chip8.framebuffer_to_vec()[0], rgba(0xFF, 0x40, 0xFF, 0xFF));
+    assert_eq!(chip8.get_register(0xF), 1);
+
+    chip8.run(6);
+    assert_eq!(chip8.get_screen_alpha(), 0x80);
+    assert_eq!(chip8.get_sample(), [0x7F, 0x80, 0x81]);
+    assert_eq!((chip8.get_sample_rate(), chip8.get_sample_looping()), (8000, false));
+    assert!(!chip8.get_megachip_mode());
+    assert_eq!(chip8.get_display_width(), 64);
+    assert!(chip8.framebuffer_to_vec().iter().all(|p| *p == 0));
+}
+
+#[test]
+fn sound_length_stops_at_the_end_of_the_rom(){
+    let mut rom = vec![
+        0x01, 0x00, 0x10, 0x00, // I := 0x1000
+        0x06, 0x00,             // loop a sample that claims 16M bytes
+        0x12, 0x06
+    ];
+    rom.resize(0x1000 - 0x200, 0);
+    rom.extend_from_slice(&[0x1F, 0x40, 0xFF, 0xFF, 0xFF, 0x00, 0x7F, 0x80, 0x81]);
+    let mut chip8 = megachip(&rom);
+    chip8.run(3);
+    assert_eq!(chip8.get_sample(), [0x7F, 0x80, 0x81]);
+    assert!(chip8.get_sample_looping());
+}
+
+#[test]
+fn other_platforms_have_no_framebuffer(){
+    let mut chip8 = Chip8::from_bytes(&[0x00, 0x11, 0x12, 0x02]);
+    chip8.run(4);
+    assert!(!chip8.get_megachip_mode());
+    assert!(chip8.framebuffer_to_vec().is_empty());
+    assert!(chip8.get_framebuffer().is_null());
+}
+
+#[test]
+fn save_states_round_trip(){
+    let mut rom = vec![
+        0x00, 0x11,
+        0xA3, 0x00, 0x02, 0x01, // palette entry 1 from 0x300
+        0xA3, 0x04, 0x03, 0x01, 0x05, 0x80,
+        0xD0, 0x00,
+        0x12, 0x0E
+    ];
+    rom.resize(0x100, 0);
+    rom.extend_from_slice(&[0xFF, 0x80, 0x20, 0xF0, 0x01]);
+    let mut chip8 = megachip(&rom);
+    chip8.run(6);
+    let state = SaveState::from_json(&chip8.save_state().to_json()).unwrap();
+    assert_eq!(state.state_hash(), chip8.state_hash());
+    chip8.run(1);
+    assert_eq!(chip8.framebuffer_to_vec()[0], rgba(0x80, 0x20, 0xF0, 0xFF));
+    assert_ne!(chip8.state_hash(), state.state_hash());
+
+    chip8.load_state(&state).unwrap();
+    assert_eq!(chip8.framebuffer_to_vec()[0], 0);
+    assert_eq!(chip8.state_hash(), state.state_hash());
+    assert_eq!(chip8.incremental_state_hash(), state.state_hash());
+    assert!(chip8.get_megachip_mode());
+    assert_eq!(chip8.get_screen_alpha(), 0x80);
+    // the palette and sprite size came back with it
+    chip8.run(1);
+    assert_eq!(chip8.framebuffer_to_vec()[0], rgba(0x80, 0x20, 0xF0, 0xFF));
+
+    let json = chip8.save_state().to_json().replace("\"sprite_width\":1", "\"sprite_width\":0");
+    assert!(SaveState::from_json(&json).is_err());
+}
+
+#[test]
+fn save_states_keep_a_24_bit_index(){
+    let mut chip8 = megachip(&[0x01, 0x12, 0x34, 0x56, 0x12, 0x04]);
+    chip8.run(2);
+    assert_eq!(chip8.get_index(), 0x123456);
+    let state = SaveState::from_json(&chip8.save_state().to_json()).unwrap();
+    let mut other = megachip(&[0x01, 0x12, 0x34, 0x56, 0x12, 0x04]);
+    other.load_state(&state).unwrap();
+    assert_eq!(other.get_index(), 0x123456);
+    assert_eq!(other.state_hash(), chip8.state_hash());
+
+    // only MEGA-CHIP reaches past 64K
+    let classic = Chip8::from_bytes(&[0x12, 0x00]);
+    let json = classic.save_state().to_json().replace("\"index\":0", "\"index\":1193046");
+    assert!(SaveState::from_json(&json).is_err());
+}
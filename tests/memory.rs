@@ -0,0 +1,33 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::memory::RegionKind;
+
+#[test]
+fn reads_and_writes_in_bulk(){
+    let mut chip8 = Chip8::from_bytes(&[0x60, 0x01, 0x12, 0x02]);
+    assert_eq!(chip8.read_mem(0x200, 4), [0x60, 0x01, 0x12, 0x02]);
+    assert_eq!(chip8.read_mem(0x050, 5), [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    assert_eq!(chip8.read_mem(0xFFF, 2), [0x00, 0x00]);
+
+    let before = chip8.state_hash();
+    chip8.write_mem(0xFFF, &[0xAB, 0xCD]);
+    assert_eq!(chip8.read_mem(0xFFF, 2), [0xAB, 0xCD]);
+    assert_eq!(chip8.get_mem_at(0x000), 0xCD);
+    assert_ne!(chip8.state_hash(), before);
+
+    // patched code is what runs next
+    chip8.write_mem(0x201, &[0x07]);
+    chip8.run(1);
+    assert_eq!(chip8.get_register(0), 7);
+}
+
+#[test]
+fn labels_regions(){
+    let chip8 = Chip8::from_bytes(&[0; 0x10]);
+    let map = chip8.get_memory_map();
+    let kinds: Vec<RegionKind> = map.iter().map(|r| r.get_kind()).collect();
+    assert_eq!(kinds, [RegionKind::Interpreter, RegionKind::Font, RegionKind::Program, RegionKind::Stack]);
+    assert_eq!((map[1].get_start(), map[1].get_end()), (0x050, 0x0A0));
+    assert_eq!((map[2].get_start(), map[2].get_end()), (0x200, 0x210));
+    assert!(map[3].contains(0xEA0) && !map[3].contains(0xED0));
+    assert!(chip8.memory_map_to_json().starts_with(r#"[{"kind":"Interpreter","start":0,"end":512}"#));
+}
@@ -0,0 +1,96 @@
+#![allow(clippy::needless_return)]
+use chip8_emulator::{Chip8, KeyState};
+use chip8_emulator::movie::Movie;
+use chip8_emulator::analyzer::Platform;
+use chip8_emulator::patch::RomPatch;
+
+const SNAKE: &[u8] = include_bytes!("../static/roms/snake.ch8");
+const PARTICLES: &[u8] = include_bytes!("../static/roms/particles.ch8");
+
+fn snapshot(chip8: &Chip8) -> Vec<usize> {
+    let mut state = vec![chip8.get_pc(), chip8.get_index()];
+    for i in 0..16{
+        state.push(chip8.get_register(i) as usize);
+    }
+    for i in 0..4096{
+        state.push(chip8.get_mem_at(i) as usize);
+    }
+    return state;
+}
+
+#[test]
+fn replay_matches_recording(){
+    let mut chip8 = Chip8::from_bytes(SNAKE);
+    chip8.set_seed(1234);
+    chip8.start_recording();
+    for frame in 0..300{
+        if frame % 40 == 10{
+            chip8.queue_key_event(frame / 40 % 4 * 2 + 2, KeyState::ON, frame as f64);
+        }
+        if frame % 40 == 20{
+            chip8.queue_key_event(frame / 40 % 4 * 2 + 2, KeyState::OFF, frame as f64);
+        }
+        chip8.run_frame(10);
+    }
+    let movie = chip8.stop_recording().unwrap();
+    assert!(movie.event_count() > 0);
+    assert_eq!(movie.get_frames(), 300);
+    let expected = snapshot(&chip8);
+
+    let mut replay = Chip8::from_bytes(SNAKE);
+    replay.set_seed(99);
+    replay.play_movie(Movie::from_json(&movie.to_json()).unwrap()).unwrap();
+    assert_eq!(replay.get_seed(), 1234);
+    for _ in 0..300{
+        assert!(replay.is_playing());
+        replay.run_frame(10);
+    }
+    assert!(!replay.is_playing());
+    assert_eq!(snapshot(&replay), expected);
+}
+
+#[test]
+fn movie_is_bound_to_rom(){
+    let mut chip8 = Chip8::from_bytes(SNAKE);
+    chip8.start_recording();
+    chip8.run_frame(10);
+    let movie = chip8.stop_recording().unwrap();
+    let mut other = Chip8::from_bytes(PARTICLES);
+    assert!(other.play_movie(movie).is_err());
+    assert!(!other.is_playing());
+}
+
+#[test]
+fn movie_is_bound_to_machine(){
+    let mut chip8 = Chip8::from_bytes(SNAKE);
+    chip8.set_platform(Platform::Chip8X);
+    chip8.add_patch(&RomPatch::from_codes("301:07", None).unwrap()).unwrap();
+    chip8.start_recording();
+    chip8.run_frame(10);
+    let movie = Movie::from_json(&chip8.stop_recording().unwrap().to_json()).unwrap();
+    assert_eq!((movie.get_platform(), movie.get_load_address(), movie.get_patch_count()), (Platform::Chip8X, 0x300, 1));
+
+    let mut other = Chip8::from_bytes(SNAKE);
+    assert!(other.play_movie(movie.clone()).is_err());
+    other.set_platform(Platform::Chip8X);
+    assert!(other.play_movie(movie.clone()).is_err());
+    other.add_patch(&RomPatch::from_codes("301:08", None).unwrap()).unwrap();
+    assert!(other.play_movie(movie.clone()).is_err());
+    other.clear_patches();
+    other.add_patch(&RomPatch::from_codes("301:07", None).unwrap()).unwrap();
+    other.play_movie(movie).unwrap();
+    assert!(other.is_playing());
+}
+
+#[test]
+fn seed_drives_random(){
+    let mut a = Chip8::from_bytes(PARTICLES);
+    let mut b = Chip8::from_bytes(PARTICLES);
+    a.set_seed(7);
+    b.set_seed(7);
+    for _ in 0..60{
+        a.run_frame(10);
+        b.run_frame(10);
+    }
+    assert_eq!(snapshot(&a), snapshot(&b));
+}
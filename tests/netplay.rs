@@ -0,0 +1,74 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::netplay::{KeypadHalf, LoopbackTransport, NetplaySession};
+
+const PONG: &[u8] = include_bytes!("../static/roms/pong.ch8");
+
+fn sessions() -> (NetplaySession<LoopbackTransport>, NetplaySession<LoopbackTransport>) {
+    let (left_link, right_link) = LoopbackTransport::pair();
+    let mut left = Chip8::from_bytes(PONG);
+    let mut right = Chip8::from_bytes(PONG);
+    left.set_seed(42);
+    right.set_seed(42);
+    let mut left = NetplaySession::new(left, left_link, KeypadHalf::Left);
+    let mut right = NetplaySession::new(right, right_link, KeypadHalf::Right);
+    left.set_hash_interval(10);
+    right.set_hash_interval(10);
+    (left, right)
+}
+
+#[test]
+fn peers_stay_in_sync(){
+    let (mut left, mut right) = sessions();
+    // no input delay, so left always has to predict right's input
+    left.set_input_delay(0);
+    right.set_input_delay(0);
+    for frame in 0..400_u64{
+        // left paddle on 1/4, right paddle on C/D
+        let left_keys = if frame % 50 < 25 {1 << 0x1} else {1 << 0x4};
+        let right_keys = if frame % 70 < 35 {1 << 0xD} else {1 << 0xC};
+        assert!(left.advance(left_keys));
+        assert!(right.advance(right_keys));
+    }
+    for _ in 0..20{
+        left.advance(0);
+        right.advance(0);
+    }
+    assert!(left.rollbacks() > 0);
+    assert_eq!(left.desync_frame(), None);
+    assert_eq!(right.desync_frame(), None);
+    assert_eq!(left.frame(), right.frame());
+    assert_eq!(left.chip8().save_state(), right.chip8().save_state());
+}
+
+#[test]
+fn desync_is_detected(){
+    let (mut left, mut right) = sessions();
+    for _ in 0..30{
+        left.advance(0);
+        right.advance(0);
+    }
+    // one peer silently runs an extra instruction
+    left.chip8_mut().tick();
+    for _ in 0..30{
+        left.advance(0);
+        right.advance(0);
+    }
+    let frame = left.desync_frame().expect("desync reported");
+    assert!(frame >= 30);
+    assert_eq!(right.desync_frame(), Some(frame));
+}
+
+#[test]
+fn stalls_without_remote_input(){
+    let (mut left, _right) = sessions();
+    left.set_max_rollback(5);
+    let mut advanced = 0;
+    for _ in 0..20{
+        if left.advance(0){
+            advanced += 1;
+        }
+    }
+    // the input delay covers the first frames, then prediction runs out
+    assert_eq!(advanced, 2 + 5);
+    assert_eq!(left.confirmed_frame(), 2);
+}
@@ -0,0 +1,81 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::octo::OctoCartridge;
+use chip8_emulator::quirks::Quirks;
+
+const SOURCE: &str = ": main\n  v0 := 1\n  loop again\n";
+
+// Lays the payload out the way Octo does: label pixels in the high bits of
+// each palette index, two payload bits in the low ones
+fn cartridge(json: &str) -> Vec<u8> {
+    let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(json.as_bytes());
+    image(&payload)
+}
+
+fn image(payload: &[u8]) -> Vec<u8> {
+    let (width, height) = (128_u16, 80_u16);
+    let mut pixels = vec![0_u8; width as usize * height as usize];
+    for (i, pixel) in pixels.iter_mut().enumerate(){
+        let label = ((i / 7) % 4) as u8;
+        let byte = payload.get(i / 4).copied().unwrap_or(0);
+        let bits = (byte >> (6 - 2 * (i % 4))) & 3;
+        *pixel = (label << 2) | bits;
+    }
+    let palette: Vec<u8> = (0..16).flat_map(|i| [i * 16, i * 8, 255 - i * 16]).collect();
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, width, height, &palette).unwrap();
+        let frame = gif::Frame::from_indexed_pixels(width, height, pixels, None);
+        encoder.write_frame(&frame).unwrap();
+    }
+    gif
+}
+
+#[test]
+fn reads_cartridge_gif(){
+    let json = format!(r##"{{"program": {:?}, "options": {{
+        "tickrate": 500, "fillColor": "#FFFFFF", "backgroundColor": "#000000",
+        "shiftQuirks": true, "loadStoreQuirks": true, "clipQuirks": true, "vBlankQuirks": true
+    }}}}"##, SOURCE);
+    let cart = OctoCartridge::from_gif(&cartridge(&json)).unwrap();
+    assert_eq!(cart.get_source(), SOURCE);
+    assert_eq!(cart.assemble().unwrap().get_rom(), [0x60, 0x01, 0x12, 0x02]);
+    let options = cart.get_options();
+    assert_eq!(options.get_tick_rate(), 500);
+    assert_eq!(options.get_pixel_colors(), ["#000000", "#FFFFFF", "#FF6600", "#662200"]);
+    assert_eq!(options.get_quirks(), Quirks::default());
+
+    let mut chip8 = Chip8::from_bytes(&[0x12, 0x00]);
+    chip8.set_quirks(Quirks::vip());
+    chip8.apply_octo_options(&options);
+    assert_eq!(chip8.get_quirks(), Quirks::default());
+}
+
+#[test]
+fn rejects_broken_cartridges(){
+    assert!(OctoCartridge::from_gif(b"not a gif").is_err());
+    let gif = cartridge("{\"options\": {}}");
+    assert!(OctoCartridge::from_gif(&gif).is_err());
+    // claims more payload than the image holds
+    let mut payload = 100_000_u32.to_be_bytes().to_vec();
+    payload.extend_from_slice(b"{\"program\": \"\"}");
+    assert_eq!(OctoCartridge::from_gif(&image(&payload)).unwrap_err(), "Cartridge payload is truncated");
+}
+
+#[test]
+fn source_with_options_json(){
+    let plain = OctoCartridge::from_source(SOURCE, None).unwrap();
+    let options = plain.get_options();
+    // Octo's own defaults: XO-CHIP quirks, 20 cycles a frame
+    assert_eq!(options.get_quirks(), Quirks::xochip());
+    assert_eq!(options.get_tick_rate(), 20);
+    assert_eq!(options.get_keymap().map_key("ArrowUp"), Some(5));
+    assert_eq!(options.get_keymap().map_key("KeyX"), Some(0));
+
+    let json = r#"{"tickrate": 7, "jumpQuirks": true, "logicQuirks": true, "maxSize": 3584}"#;
+    let cart = OctoCartridge::from_source(SOURCE, Some(json.to_string())).unwrap();
+    let quirks = cart.get_options().get_quirks();
+    assert!(quirks.jump_uses_vx && quirks.logic_resets_vf && quirks.shift_uses_vy);
+    assert_eq!(cart.get_options().profile().get_quirks(), quirks);
+    assert!(OctoCartridge::from_source(SOURCE, Some(r#"{"tickrate": "fast"}"#.to_string())).is_err());
+}
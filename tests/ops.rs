@@ -1,3 +1,4 @@
+#![allow(clippy::needless_return, clippy::needless_range_loop)]
 use wasm_bindgen_test::{wasm_bindgen_test};
 use wasm_bindgen::JsValue;
 use js_sys::Uint8Array;
@@ -5,6 +6,7 @@ use chip8_emulator::Chip8;
 use chip8_emulator::KeyState;
 use chip8_emulator::fonts::{FONT_OFFSET};
 use chip8_emulator::instructions::Instruction;
+use chip8_emulator::quirks::Quirks;
 fn empty_program() -> Uint8Array {
     return Uint8Array::new(&JsValue::from(43));
 }
@@ -67,7 +69,7 @@ fn push_test(){
     let mut chip8 = Chip8::new(&empty_program());
     chip8.push(instr);
     assert_eq!(chip8.get_pc(),0x390);
-    assert_eq!(chip8.get_top_of_stack(),0x200);
+    assert_eq!(chip8.get_top_of_stack(),Some(0x200));
 }
 
 
@@ -340,6 +342,23 @@ fn test_get_key(){
     assert_eq!(chip8.get_pc(), 0x200 - 2);
     chip8.set_key_state(2, KeyState::ON);
     chip8.f(Instruction::from_str("F00A"));
+    assert_eq!(chip8.get_pc(), 0x200 - 4);
+    assert_eq!(chip8.get_register(0),0);
+    chip8.set_key_state(2, KeyState::OFF);
+    chip8.f(Instruction::from_str("F00A"));
+    assert_eq!(chip8.get_pc(), 0x200 - 4);
+    assert_eq!(chip8.get_register(0),2);
+}
+
+#[wasm_bindgen_test]
+fn test_get_key_on_press_quirk(){
+    let mut chip8 = Chip8::new(&empty_program());
+    let mut quirks = Quirks::new();
+    quirks.key_wait_on_press = true;
+    chip8.set_quirks(quirks);
+    chip8.set_key_state(2, KeyState::ON);
+    chip8.f(Instruction::from_str("F00A"));
+    assert_eq!(chip8.get_pc(), 0x200);
     assert_eq!(chip8.get_register(0),2);
 }
 
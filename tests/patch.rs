@@ -0,0 +1,133 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::patch::{crc32, RomPatch};
+
+// V0 := 1, V1 := 2, spin
+const ROM: [u8; 6] = [0x60, 0x01, 0x61, 0x02, 0x12, 0x04];
+
+fn sha1(rom: &[u8]) -> String {
+    Chip8::from_bytes(rom).get_rom_hash()
+}
+
+fn bps_number(out: &mut Vec<u8>, mut n: usize){
+    loop {
+        let x = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0{
+            out.push(0x80 | x);
+            return;
+        }
+        out.push(x);
+        n -= 1;
+    }
+}
+
+fn bps(source: &[u8], target: &[u8], commands: &[u8]) -> Vec<u8> {
+    let mut out = b"BPS1".to_vec();
+    bps_number(&mut out, source.len());
+    bps_number(&mut out, target.len());
+    bps_number(&mut out, 0);
+    out.extend_from_slice(commands);
+    out.extend_from_slice(&crc32(source).to_le_bytes());
+    out.extend_from_slice(&crc32(target).to_le_bytes());
+    let crc = crc32(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+#[test]
+fn applies_codes(){
+    let patch = RomPatch::from_codes("0x201:07, 203:09\n208:AA", Some(sha1(&ROM).to_uppercase())).unwrap();
+    assert_eq!(patch.apply(&ROM).unwrap(), [0x60, 0x07, 0x61, 0x09, 0x12, 0x04, 0x00, 0x00, 0xAA]);
+    assert!(patch.apply(&[0x00]).is_err());
+    assert!(RomPatch::from_codes("201=07", None).is_err());
+    assert!(RomPatch::from_codes("100:07", None).unwrap().apply(&ROM).is_err());
+    assert!(RomPatch::from_codes("1000:07", None).is_err());
+    assert!(RomPatch::from_codes("201:107", None).is_err());
+}
+
+#[test]
+fn applies_ips(){
+    let mut ips = b"PATCH".to_vec();
+    // two bytes at offset 1
+    ips.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0x07, 0x61]);
+    // three 0xEE from offset 6
+    ips.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0xEE]);
+    ips.extend_from_slice(b"EOF");
+    let patch = RomPatch::from_ips(&ips, None).unwrap();
+    assert_eq!(patch.apply(&ROM).unwrap(), [0x60, 0x07, 0x61, 0x02, 0x12, 0x04, 0xEE, 0xEE, 0xEE]);
+    // a truncation length after EOF
+    ips.extend_from_slice(&[0x00, 0x00, 0x04]);
+    assert_eq!(RomPatch::from_ips(&ips, None).unwrap().apply(&ROM).unwrap(), [0x60, 0x07, 0x61, 0x02]);
+
+    assert!(RomPatch::from_ips(b"PATCH\x00\x00\x01\x00", None).is_err());
+    assert!(RomPatch::from_ips(b"PATCH\xFF\x00\x00\x00\x01\x00EOF", None).is_err());
+    assert!(RomPatch::from_ips(b"IPS", None).is_err());
+    // offsets count from the load address, so the last 0x200 bytes of
+    // memory are out of reach
+    let past_end = RomPatch::from_ips(b"PATCH\x00\x0E\x00\x00\x01\x00EOF", None).unwrap();
+    assert!(past_end.apply(&ROM).is_err());
+}
+
+#[test]
+fn applies_bps(){
+    let target = [0x60, 0x05, 0x61, 0x02, 0x12, 0x04, 0x12, 0x04];
+    // SourceRead 1, TargetRead 1, SourceRead 4, SourceCopy 2 from offset 4
+    let mut commands = vec![0x80, 0x81, 0x05, 0x8C, 0x86];
+    bps_number(&mut commands, 4 << 1);
+    let patch = RomPatch::from_bps(&bps(&ROM, &target, &commands), None).unwrap();
+    assert_eq!(patch.apply(&ROM).unwrap(), target);
+    assert_eq!(patch.apply(&target).unwrap_err(), "BPS patch does not match this ROM");
+
+    // TargetCopy overlapping what it writes, repeating the last two bytes
+    let repeated = [0x60, 0x01, 0x61, 0x02, 0x12, 0x04, 0x12, 0x04, 0x12, 0x04];
+    let mut commands = vec![0x94, 0x8F];
+    bps_number(&mut commands, 4 << 1);
+    assert_eq!(RomPatch::from_bps(&bps(&ROM, &repeated, &commands), None).unwrap().apply(&ROM).unwrap(), repeated);
+
+    let mut corrupt = bps(&ROM, &target, &[0x80]);
+    corrupt[5] ^= 1;
+    assert!(RomPatch::from_bps(&corrupt, None).is_err());
+    // ends short of the target
+    assert!(RomPatch::from_bps(&bps(&ROM, &target, &[0x80]), None).unwrap().apply(&ROM).is_err());
+}
+
+#[test]
+fn patches_loaded_rom(){
+    let mut chip8 = Chip8::from_bytes(&ROM);
+    let hash = chip8.get_rom_hash();
+    chip8.run(2);
+    assert_eq!(chip8.get_register(0), 1);
+
+    chip8.add_patch(&RomPatch::from_codes("201:07", Some(hash.clone())).unwrap()).unwrap();
+    chip8.add_patch(&RomPatch::from_codes("203:09", None).unwrap()).unwrap();
+    assert_eq!(chip8.get_pc(), 0x200);
+    chip8.run(2);
+    assert_eq!((chip8.get_register(0), chip8.get_register(1)), (7, 9));
+    assert_eq!(chip8.get_rom_hash(), hash);
+
+    // survives a reset, verified against the unpatched ROM
+    chip8.reset();
+    chip8.run(2);
+    assert_eq!(chip8.get_register(0), 7);
+    let other = RomPatch::from_codes("201:03", Some(sha1(&[0x00]))).unwrap();
+    assert!(chip8.add_patch(&other).is_err());
+    assert_eq!(chip8.get_patch_count(), 2);
+
+    chip8.clear_patches();
+    chip8.run(2);
+    assert_eq!(chip8.get_register(0), 1);
+
+    let json = RomPatch::from_codes("201:07", Some(hash)).unwrap().to_json();
+    chip8.add_patch(&RomPatch::from_json(&json).unwrap()).unwrap();
+    chip8.load_rom(&ROM);
+    assert_eq!(chip8.get_patch_count(), 0);
+}
+
+#[test]
+fn json_patches_are_validated(){
+    assert!(RomPatch::from_json(r#"{"rom_sha1":null,"edits":{"Bps":[1,2,3]}}"#).is_err());
+    assert!(RomPatch::from_json(r#"{"rom_sha1":null,"edits":{"Ips":[1,2,3]}}"#).is_err());
+    assert!(RomPatch::from_json(r#"{"rom_sha1":null,"edits":{"Codes":[[8192,1]]}}"#).is_err());
+    let valid = RomPatch::from_codes("201:07", None).unwrap();
+    assert_eq!(RomPatch::from_json(&valid.to_json()).unwrap(), valid);
+}
@@ -0,0 +1,45 @@
+use chip8_emulator::Chip8;
+
+// 200: call 206, jump 200; 206: V0 += 1, V1 := V0, return
+const PROGRAM: [u8; 12] = [0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x70, 0x01, 0x81, 0x00, 0x00, 0xEE];
+
+#[test]
+fn profiles_only_when_enabled(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.run(10);
+    assert!(chip8.profile_report().is_none());
+    chip8.set_profiling(true);
+    chip8.clear_profile();
+    assert_eq!(chip8.profile_report().unwrap().get_cycles(), 0);
+}
+
+#[test]
+fn reports_opcodes_addresses_and_subroutines(){
+    let mut chip8 = Chip8::from_bytes(&PROGRAM);
+    chip8.set_profiling(true);
+    // two full trips round the loop, then stop inside the subroutine
+    chip8.run(12);
+    let report = chip8.profile_report().unwrap();
+    assert_eq!(report.get_cycles(), 12);
+
+    let opcodes: Vec<(&str, u64)> = report.opcodes().iter().map(|o| (o.opcode.as_str(), o.count)).collect();
+    assert_eq!(opcodes, [("2NNN", 3), ("7XNN", 3), ("00EE", 2), ("1NNN", 2), ("8XY0", 2)]);
+    assert_eq!((report.addresses()[0].addr, report.addresses()[0].count), (0x200, 3));
+
+    let subroutines = report.subroutines();
+    assert_eq!(subroutines.len(), 1);
+    assert_eq!((subroutines[0].addr, subroutines[0].calls, subroutines[0].cycles), (0x206, 2, 8));
+    assert_eq!(subroutines[0].name, "0x206");
+    assert!(report.to_json().contains(r#""opcodes":[{"opcode":"2NNN","count":3}"#));
+}
+
+#[test]
+fn names_subroutines_from_symbols(){
+    let mut chip8 = Chip8::from_bytes(&[]);
+    chip8.load_octo(": main loop step again : step v0 += 1 return").unwrap();
+    chip8.set_profiling(true);
+    chip8.run(40);
+    let report = chip8.profile_report().unwrap();
+    assert_eq!(report.subroutines()[0].name, "step");
+    assert_eq!(report.subroutines()[0].calls, 10);
+}
@@ -0,0 +1,64 @@
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::{Chip8, PixelState};
+
+fn with_quirks(rom: &[u8], quirks: Quirks) -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(rom);
+    chip8.set_quirks(quirks);
+    chip8
+}
+
+#[test]
+fn memory_ops_move_index(){
+    // I = 0x300, store V0-V2, load V0-V1
+    let rom = [0xA3, 0x00, 0xF2, 0x55, 0xF1, 0x65];
+    let mut chip8 = with_quirks(&rom, Quirks::default());
+    chip8.run(3);
+    assert_eq!(chip8.get_index(), 0x300);
+    let mut chip8 = with_quirks(&rom, Quirks::vip());
+    chip8.run(3);
+    assert_eq!(chip8.get_index(), 0x305);
+}
+
+#[test]
+fn offset_jump_register(){
+    // V0 = 2, V3 = 8, B310
+    let rom = [0x60, 0x02, 0x63, 0x08, 0xB3, 0x10];
+    let mut chip8 = with_quirks(&rom, Quirks::default());
+    chip8.run(3);
+    assert_eq!(chip8.get_pc(), 0x312);
+    let mut chip8 = with_quirks(&rom, Quirks::schip());
+    chip8.run(3);
+    assert_eq!(chip8.get_pc(), 0x318);
+}
+
+#[test]
+fn sprites_clip_or_wrap(){
+    // V0 = 60, V1 = 31, I = 0x208, draw two rows of 0xFF
+    let rom = [0x60, 0x3C, 0x61, 0x1F, 0xA2, 0x08, 0xD0, 0x12, 0xFF, 0xFF];
+    let mut clipped = with_quirks(&rom, Quirks::default());
+    clipped.run(4);
+    let mut wrapped = with_quirks(&rom, Quirks::xochip());
+    wrapped.run(4);
+    let pixel = |chip8: &Chip8, x: usize, y: usize| unsafe { *chip8.get_display().add(y * 64 + x) };
+    assert_eq!(pixel(&clipped, 63, 31), PixelState::ON);
+    assert_eq!(pixel(&wrapped, 63, 31), PixelState::ON);
+    // the part past the right edge neither wraps nor bleeds into the next row
+    assert_eq!(pixel(&clipped, 0, 31), PixelState::OFF);
+    assert_eq!(pixel(&clipped, 0, 0), PixelState::OFF);
+    assert_eq!(pixel(&wrapped, 0, 31), PixelState::ON);
+    assert_eq!(pixel(&wrapped, 0, 0), PixelState::ON);
+    assert_eq!(pixel(&wrapped, 63, 0), PixelState::ON);
+}
+
+#[test]
+fn sprites_clip_without_bleeding(){
+    // V0 = 60, V1 = 0, I = 0x208, one row of 0xFF. Before the quirks the
+    // four pixels past the right edge were drawn at the start of row 1.
+    let rom = [0x60, 0x3C, 0x61, 0x00, 0xA2, 0x08, 0xD0, 0x11, 0xFF];
+    let mut chip8 = with_quirks(&rom, Quirks::default());
+    chip8.run(4);
+    let pixel = |x: usize, y: usize| unsafe { *chip8.get_display().add(y * 64 + x) };
+    assert_eq!(pixel(63, 0), PixelState::ON);
+    assert!((0..4).all(|x| pixel(x, 1) == PixelState::OFF));
+    assert!((0..4).all(|x| pixel(x, 0) == PixelState::OFF));
+}
@@ -0,0 +1,161 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::instructions::Instruction;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::recompiler::{compile_block, is_straight_line, BlockRuntime, JitRegs};
+use wasmi::{Engine, Linker, Memory, MemoryType, Module, Store, TypedFunc};
+
+const ROMS: [&[u8]; 7] = [
+    include_bytes!("../static/roms/trip8.ch8"),
+    include_bytes!("../static/roms/life.ch8"),
+    include_bytes!("../static/roms/division.ch8"),
+    include_bytes!("../static/roms/particles.ch8"),
+    include_bytes!("../static/roms/stars.ch8"),
+    include_bytes!("../static/roms/triangle.ch8"),
+    include_bytes!("../static/roms/corax-test.ch8")
+];
+
+// every combination of the quirks the ALU looks at
+fn quirk_profiles() -> Vec<Quirks> {
+    let mut profiles = Vec::new();
+    for shift_uses_vy in [false, true]{
+        for logic_resets_vf in [false, true]{
+            profiles.push(Quirks { shift_uses_vy, logic_resets_vf, ..Quirks::default() });
+        }
+    }
+    profiles
+}
+
+// Stands in for the browser: instantiates blocks with wasmi and copies the
+// registers in and out of its memory
+struct WasmiRuntime {
+    store: Store<()>,
+    linker: Linker<()>,
+    memory: Memory,
+    blocks: Vec<TypedFunc<i32, ()>>
+}
+
+impl WasmiRuntime {
+    fn new() -> WasmiRuntime {
+        let engine = Engine::default();
+        let mut store = Store::new(&engine, ());
+        let memory = Memory::new(&mut store, MemoryType::new(1, None).unwrap()).unwrap();
+        let mut linker = Linker::new(&engine);
+        linker.define("env", "memory", memory).unwrap();
+        WasmiRuntime { store, linker, memory, blocks: Vec::new() }
+    }
+}
+
+impl BlockRuntime for WasmiRuntime {
+    fn load(&mut self, wasm: &[u8]) -> Option<usize> {
+        let module = Module::new(self.store.engine(), wasm).unwrap();
+        let instance = self.linker.instantiate(&mut self.store, &module).unwrap()
+            .start(&mut self.store).unwrap();
+        self.blocks.push(instance.get_typed_func::<i32, ()>(&self.store, "run").unwrap());
+        Some(self.blocks.len() - 1)
+    }
+    fn call(&mut self, handle: usize, regs: &mut JitRegs){
+        self.memory.write(&mut self.store, 0, &regs.to_bytes()).unwrap();
+        self.blocks[handle].call(&mut self.store, 0).unwrap();
+        let mut bytes = [0; JitRegs::SIZE];
+        self.memory.read(&self.store, 0, &mut bytes).unwrap();
+        *regs = JitRegs::from_bytes(&bytes);
+    }
+}
+
+fn regs_of(chip8: &Chip8) -> JitRegs {
+    let mut regs = JitRegs { index: chip8.get_index() as u32, ..JitRegs::default() };
+    for i in 0..16{
+        regs.v[i] = chip8.get_register(i);
+    }
+    regs.delay_timer = chip8.delay_timer;
+    regs.sound_timer = chip8.sound_timer;
+    regs
+}
+
+#[test]
+fn every_straight_line_opcode_matches_interpreter(){
+    let mut runtime = WasmiRuntime::new();
+    let values = [0x00, 0x01, 0x7F, 0x80, 0xFE, 0xFF, 0x35];
+    for raw in 0..=0xFFFF_u16{
+        let instr = Instruction::from_raw(raw);
+        if !is_straight_line(&instr){
+            continue;
+        }
+        // register values are sampled, the opcode space is covered fully
+        let vx = values[raw as usize % values.len()];
+        let vy = values[(raw as usize / 7) % values.len()];
+        let rom = [0x60 | instr.x, vx, 0x60 | instr.y, vy, 0xA3, 0xF0, 0x6E, 0x22, 0xFE, 0x15, (raw >> 8) as u8, raw as u8];
+        // only the ALU ops look at quirks
+        let mut profiles = quirk_profiles();
+        if instr.operation != 0x8{
+            profiles.truncate(1);
+        }
+        for quirks in profiles{
+            let mut interpreted = Chip8::from_bytes(&rom);
+            interpreted.set_quirks(quirks);
+            for _ in 0..5{
+                interpreted.tick();
+            }
+            let mut regs = regs_of(&interpreted);
+            interpreted.tick();
+            let block = compile_block(&rom, 10, 1, quirks);
+            let handle = runtime.load(&block).unwrap();
+            runtime.call(handle, &mut regs);
+            assert_eq!(regs, regs_of(&interpreted), "{:04X} {:?}", raw, quirks);
+        }
+    }
+}
+
+#[test]
+fn jit_matches_interpreter_on_roms(){
+    for rom in ROMS{
+        let mut jit = Chip8::from_bytes(rom);
+        let mut interpreter = Chip8::from_bytes(rom);
+        jit.set_seed(21);
+        interpreter.set_seed(21);
+        jit.set_block_runtime(Box::new(WasmiRuntime::new()));
+        // uneven slices so blocks are cut at every possible point
+        for step in 0..3000_u32{
+            let cycles = step % 17 + 1;
+            jit.run(cycles);
+            interpreter.run(cycles);
+            assert_eq!(jit.get_cycles(), interpreter.get_cycles());
+            assert_eq!(jit.get_pc(), interpreter.get_pc());
+            assert_eq!(jit.state_hash(), interpreter.state_hash());
+            if step % 10 == 0{
+                jit.tick_timers();
+                interpreter.tick_timers();
+            }
+        }
+    }
+}
+
+#[test]
+fn rewritten_block_falls_back_and_recompiles(){
+    let rom = [
+        0x70, 0x01, // 0x200: V0 += 1
+        0x61, 0x05, // 0x202: V1 = 5, rewritten to V1 = 7 below
+        0x82, 0x14, // 0x204: V2 += V1
+        0x30, 0x40, // 0x206: skip if V0 == 0x40
+        0x12, 0x00, // 0x208: loop
+        0xA2, 0x02, // 0x20A: I = 0x202
+        0x60, 0x61, // 0x20C: V0 = 0x61
+        0x61, 0x07, // 0x20E: V1 = 0x07
+        0xF1, 0x55, // 0x210: store V0-V1 over 0x202
+        0x60, 0x00, // 0x212: V0 = 0
+        0x12, 0x00  // 0x214: loop
+    ];
+    let mut jit = Chip8::from_bytes(&rom);
+    let mut interpreter = Chip8::from_bytes(&rom);
+    jit.set_block_runtime(Box::new(WasmiRuntime::new()));
+    jit.run(100);
+    interpreter.run(100);
+    assert_eq!(jit.compiled_block_count(), 1);
+    for _ in 0..50{
+        jit.run(7);
+        interpreter.run(7);
+        assert_eq!(jit.state_hash(), interpreter.state_hash());
+    }
+    assert_eq!(jit.get_register(1), 7);
+    assert!(jit.compiled_block_count() >= 1);
+}
@@ -0,0 +1,33 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::state::SaveState;
+
+const TRIP8: &[u8] = include_bytes!("../static/roms/trip8.ch8");
+const IBM: &[u8] = include_bytes!("../static/roms/ibm.ch8");
+
+#[test]
+fn load_state_rewinds(){
+    let mut chip8 = Chip8::from_bytes(TRIP8);
+    chip8.set_seed(3);
+    for _ in 0..30{
+        chip8.run_frame(10);
+    }
+    let saved = SaveState::from_json(&chip8.save_state().to_json()).unwrap();
+    for _ in 0..30{
+        chip8.run_frame(10);
+    }
+    let later = chip8.save_state();
+    chip8.load_state(&saved).unwrap();
+    assert_eq!(chip8.get_frame(), 30);
+    for _ in 0..30{
+        chip8.run_frame(10);
+    }
+    assert_eq!(chip8.save_state(), later);
+}
+
+#[test]
+fn state_is_bound_to_rom(){
+    let saved = Chip8::from_bytes(TRIP8).save_state();
+    let mut other = Chip8::from_bytes(IBM);
+    assert!(other.load_state(&saved).is_err());
+    assert!(SaveState::from_json("{}").is_err());
+}
@@ -0,0 +1,80 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::symbols::SymbolTable;
+
+const SOURCE: &str = "
+: main
+  v0 := 1
+  loop
+    draw_paddle
+  again
+: draw_paddle
+  v1 := 2
+  v2 := 3
+  :breakpoint after_move
+  v3 := 4
+  return
+";
+
+fn loaded() -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(&[]);
+    chip8.load_octo(SOURCE).unwrap();
+    chip8
+}
+
+#[test]
+fn symbolizes_to_nearest_label(){
+    let mut table = SymbolTable::new();
+    table.add_label("main", 0x200);
+    table.add_label("draw_paddle", 0x236);
+    assert_eq!(table.symbolize(0x200), "main");
+    assert_eq!(table.symbolize(0x23A), "draw_paddle+4");
+    assert_eq!(table.symbolize(0x234), "main+52");
+    assert_eq!(table.symbolize(0x1FE), "0x1FE");
+}
+
+#[test]
+fn resolves_pc_stack_and_lines(){
+    let mut chip8 = loaded();
+    chip8.clear_breakpoints();
+    assert_eq!(chip8.symbolize_pc(), "main");
+    assert_eq!(chip8.get_source_line(), Some(3));
+    chip8.run(3);
+    assert_eq!(chip8.symbolize_pc(), "draw_paddle+2");
+    assert_eq!(chip8.get_source_line(), Some(9));
+    assert_eq!(chip8.symbolize_stack(), ["main+4"]);
+}
+
+#[test]
+fn stops_at_breakpoints(){
+    let mut chip8 = loaded();
+    let paddle = chip8.get_symbols().get_label("draw_paddle").unwrap() as usize;
+    assert_eq!(chip8.symbolize_breakpoints(), ["after_move"]);
+    chip8.run(100);
+    assert_eq!(chip8.get_break_hit(), Some(paddle + 4));
+    assert_eq!(chip8.get_register(2), 3);
+    assert_eq!(chip8.get_register(3), 0);
+
+    // continuing executes the instruction under the breakpoint
+    assert_eq!(chip8.add_breakpoint_at("draw_paddle"), Ok(paddle));
+    chip8.run(100);
+    assert_eq!(chip8.get_register(3), 4);
+    assert_eq!(chip8.get_break_hit(), Some(paddle));
+    assert_eq!(chip8.symbolize_breakpoints(), ["draw_paddle", "after_move"]);
+    assert!(chip8.add_breakpoint_at("nowhere").is_err());
+
+    chip8.reset();
+    assert_eq!(chip8.get_breakpoints().len(), 2);
+    chip8.load_rom(&[0x12, 0x00]);
+    assert!(chip8.get_breakpoints().is_empty());
+    assert_eq!(chip8.symbolize_pc(), "0x200");
+}
+
+#[test]
+fn loads_symbol_files(){
+    let json = loaded().get_symbols().to_json();
+    let mut chip8 = Chip8::from_bytes(&[]);
+    chip8.load_symbols(&json).unwrap();
+    assert_eq!(chip8.symbolize(0x202), "main+2");
+    assert_eq!(chip8.get_breakpoints().len(), 1);
+    assert!(chip8.load_symbols("{\"labels\": 3}").is_err());
+}
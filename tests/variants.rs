@@ -0,0 +1,144 @@
+use chip8_emulator::Chip8;
+use chip8_emulator::analyzer::Platform;
+use chip8_emulator::KeyState;
+use chip8_emulator::state::SaveState;
+
+fn on(platform: Platform, rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::from_bytes(rom);
+    chip8.set_platform(platform);
+    chip8
+}
+
+#[test]
+fn chip8x_colors_and_keypad(){
+    let mut chip8 = on(Platform::Chip8X, &[
+        0x02, 0xA0,             // next background
+        0x60, 0x21, 0x61, 0x05, // V0 := 21 (two more zones from column 1), V1 := 5
+        0x62, 0x10, 0xB0, 0x20, // V2 := 10 (rows 0 and 1), BXY0
+        0x63, 0x33, 0x64, 0x44, 0x53, 0x41, // 5XY1 nibble add
+        0x65, 0x07, 0xE5, 0xF2, 0x66, 0x01, // skip V6 := 1 if key 7 on pad 2
+        0xF1, 0xF8,             // tone V1
+        0x13, 0x18
+    ]);
+    assert_eq!((chip8.get_load_address(), chip8.get_pc()), (0x300, 0x300));
+    chip8.set_keypad2_state(7, KeyState::ON);
+    chip8.run(20);
+    assert_eq!(chip8.get_background_color(), 1);
+    let colors = chip8.get_foreground_colors();
+    assert_eq!(&colors[..8], [7, 5, 5, 5, 7, 7, 7, 7]);
+    assert_eq!(&colors[7 * 8..8 * 8], [7, 5, 5, 5, 7, 7, 7, 7]);
+    assert_eq!(&colors[8 * 8..9 * 8], [7; 8]);
+    assert_eq!(chip8.get_register(3), 0x77);
+    assert_eq!(chip8.get_register(6), 0);
+    assert_eq!(chip8.get_port_output(), 5);
+}
+
+#[test]
+fn chip8x_color_rows(){
+    // V0 := 20 (column 2), V1 := 3, V2 := 6, BXY2 colours rows 6 and 7
+    let mut chip8 = on(Platform::Chip8X, &[0x60, 0x14, 0x61, 0x03, 0x62, 0x06, 0xB0, 0x22, 0x13, 0x08]);
+    chip8.run(10);
+    let colors = chip8.get_foreground_colors();
+    let column = |row: usize| colors[row * 8 + 2];
+    assert_eq!((column(5), column(6), column(7), column(8)), (7, 3, 3, 7));
+}
+
+#[test]
+fn chip8e_opcodes(){
+    let mut chip8 = on(Platform::Chip8E, &[
+        0x01, 0x88, 0x60, 0xFF, // skipped
+        0x60, 0x05, 0x61, 0x03, 0x50, 0x11, 0x60, 0xFF, // 5XY1 skips when V0 > V1
+        0xA3, 0x00, 0x50, 0x12, // save V0, V1
+        0x62, 0x02, 0xF2, 0x1B, 0x63, 0xFF, // FX1B skips two bytes
+        0xBF, 0x04, 0x63, 0xFF, // branch forward to 0x21A
+        0x64, 0x03, 0xF4, 0x4F, // wait for the delay timer
+        0x00, 0xED              // stop
+    ]);
+    chip8.run(20);
+    assert_eq!(chip8.get_register(0), 5);
+    assert_eq!(chip8.read_mem(0x300, 2), [5, 3]);
+    assert_eq!(chip8.get_register(3), 0);
+    // stuck on FX4F until three frames pass
+    assert_eq!(chip8.get_pc(), 0x21C);
+    for _ in 0..3{
+        chip8.run_frame(5);
+    }
+    chip8.run(5);
+    assert_eq!(chip8.get_pc(), 0x21E);
+
+    // BBNN jumps back
+    let mut chip8 = on(Platform::Chip8E, &[0x70, 0x01, 0xBB, 0x02]);
+    chip8.run(6);
+    assert_eq!(chip8.get_register(0), 3);
+}
+
+#[test]
+fn hires_boots_into_64_rows(){
+    let mut rom = vec![0x12, 0x60];
+    rom.resize(0xC0, 0);
+    // V0 := 0, V1 := 59, draw the 0 digit down to the bottom row, spin
+    rom.extend_from_slice(&[0x60, 0x00, 0x61, 0x3B, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0xC8]);
+    let mut chip8 = on(Platform::HiresChip8, &rom);
+    assert_eq!(chip8.get_display_height(), 64);
+    chip8.run(10);
+    assert_eq!(chip8.get_pc(), 0x2C8);
+    let pixel = |chip8: &Chip8, x: usize, y: usize| unsafe { *chip8.get_display().add(y * 64 + x) };
+    assert_eq!(pixel(&chip8, 0, 59) as u8, 1);
+    assert_eq!(pixel(&chip8, 0, 63) as u8, 1);
+
+    // a classic machine takes the boot jump literally
+    let mut classic = Chip8::from_bytes(&rom);
+    classic.run(1);
+    assert_eq!(classic.get_display_height(), 32);
+    assert_eq!(classic.get_pc(), 0x260);
+}
+
+#[test]
+fn classic_platforms_ignore_variant_opcodes(){
+    let mut chip8 = Chip8::from_bytes(&[0x02, 0xA0, 0x12, 0x02]);
+    chip8.run(4);
+    assert_eq!(chip8.get_background_color(), 0);
+    assert_eq!(chip8.get_platform(), Platform::Chip8);
+}
+
+#[test]
+fn save_states_keep_variant_state(){
+    // CHIP-8E: wait three frames, then V5 := 1
+    let mut chip8 = on(Platform::Chip8E, &[0x64, 0x03, 0xF4, 0x4F, 0x65, 0x01, 0x12, 0x06]);
+    let start = chip8.save_state();
+    chip8.run(5);
+    assert_eq!(chip8.get_pc(), 0x202);
+    // rewinding to before FX4F also forgets that it was waiting
+    chip8.load_state(&start).unwrap();
+    chip8.run(5);
+    assert_eq!(chip8.get_pc(), 0x202);
+    for _ in 0..3{
+        chip8.run_frame(1);
+    }
+    chip8.run(2);
+    assert_eq!(chip8.get_register(5), 1);
+
+    let mut chip8 = on(Platform::Chip8X, &[0x02, 0xA0, 0x13, 0x02]);
+    chip8.set_keypad2_state(3, KeyState::ON);
+    let state = SaveState::from_json(&chip8.save_state().to_json()).unwrap();
+    assert_eq!(state.state_hash(), chip8.state_hash());
+    chip8.run(1);
+    assert_ne!(chip8.state_hash(), state.state_hash());
+    chip8.load_state(&state).unwrap();
+    assert_eq!(chip8.get_background_color(), 0);
+    assert_eq!(chip8.state_hash(), state.state_hash());
+
+    // a state only loads into the platform it came from
+    let mut classic = Chip8::from_bytes(&[0x02, 0xA0, 0x13, 0x02]);
+    assert!(classic.load_state(&state).is_err());
+}
+
+#[test]
+fn profile_layouts_survive_platform_changes(){
+    let mut chip8 = Chip8::from_bytes(&[0x12, 0x00]);
+    chip8.set_memory_layout(0x600, 0x600).unwrap();
+    chip8.set_platform(Platform::Chip8X);
+    assert_eq!(chip8.get_load_address(), 0x600);
+    chip8.set_platform(Platform::Chip8);
+    assert_eq!(chip8.get_pc(), 0x600);
+}